@@ -0,0 +1,75 @@
+use {Meter, Report};
+
+
+/// Callback registered by `Meter::on_threshold`
+pub type AlertCallback = Box<dyn FnMut(&Report) + Send>;
+
+pub(crate) struct Threshold {
+    cpu_pct: f32,
+    rss_bytes: u64,
+    callback: AlertCallback,
+}
+
+impl Meter {
+    /// Register a callback called when a report exceeds a threshold
+    ///
+    /// After each `scan_and_report()`, the callback receives the fresh
+    /// report if `process_cpu_usage` is above `cpu_pct` or `memory_rss`
+    /// is above `rss_bytes`. It's called on every such report, not only
+    /// when the threshold is crossed first. Pass `f32::INFINITY` or
+    /// `u64::MAX` to ignore one of the limits.
+    ///
+    /// Multiple thresholds may be registered, callbacks are called
+    /// in order of registration.
+    pub fn on_threshold(&mut self, cpu_pct: f32, rss_bytes: u64,
+        callback: AlertCallback)
+    {
+        self.thresholds.push(Threshold { cpu_pct, rss_bytes, callback });
+    }
+    /// Remove all callbacks registered by `on_threshold`
+    pub fn clear_thresholds(&mut self) {
+        self.thresholds.clear();
+    }
+    pub(crate) fn check_thresholds(&mut self, report: &Report) {
+        for threshold in &mut self.thresholds {
+            if report.process_cpu_usage > threshold.cpu_pct ||
+                report.memory_rss > threshold.rss_bytes
+            {
+                (threshold.callback)(report);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use report::test::{snapshot, meter_with};
+
+    #[test]
+    fn thresholds() {
+        let mut last = snapshot(200, 0);
+        last.memory_rss = 4096;
+        let mut meter = meter_with(snapshot(100, 0), last);
+        let report = meter.report().unwrap();
+
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let log = fired.clone();
+        meter.on_threshold(50., 1024, Box::new(move |report| {
+            log.lock().unwrap().push(("rss", report.memory_rss));
+        }));
+        let log = fired.clone();
+        meter.on_threshold(50., u64::MAX, Box::new(move |report| {
+            log.lock().unwrap().push(("cpu", report.memory_rss));
+        }));
+        meter.check_thresholds(&report);
+        meter.check_thresholds(&report);
+        assert_eq!(*fired.lock().unwrap(),
+                   vec![("rss", 4096), ("rss", 4096)]);
+
+        meter.clear_thresholds();
+        meter.check_thresholds(&report);
+        assert_eq!(fired.lock().unwrap().len(), 2);
+    }
+}
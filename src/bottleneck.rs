@@ -0,0 +1,136 @@
+use Report;
+
+
+/// Dominant resource over the report's interval, see `Report::bottleneck`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature="serde", derive(Serialize))]
+pub enum Bottleneck {
+    /// No resource is near its threshold
+    None,
+    /// Process is saturating at least a single core
+    Cpu,
+    /// Process is swapping or thrashing
+    Memory,
+    /// Process is doing a lot of block IO
+    Io,
+}
+
+/// Thresholds used by `Report::bottleneck_with`
+///
+/// Use `BottleneckThresholds::default()` and update the fields you need.
+#[derive(Debug, Clone)]
+pub struct BottleneckThresholds {
+    /// Process' CPU usage (100% is a single core), default is `90.0`
+    pub cpu_usage: f32,
+    /// Swapped out memory in bytes, default is 256 MiB
+    pub memory_swap: u64,
+    /// Value of `Report::thrashing_indicator`, default is `0.3`
+    pub thrashing: f32,
+    /// Block IO (read plus written) bytes per second, default is 50 MiB
+    pub disk_bytes: f32,
+    /// IO operations (read plus write syscalls) per second,
+    /// default is `10000`
+    pub io_ops: f32,
+}
+
+impl Default for BottleneckThresholds {
+    fn default() -> BottleneckThresholds {
+        BottleneckThresholds {
+            cpu_usage: 90.,
+            memory_swap: 256 << 20,
+            thrashing: 0.3,
+            disk_bytes: (50 << 20) as f32,
+            io_ops: 10000.,
+        }
+    }
+}
+
+impl Report {
+    /// Classifies the interval as CPU-, memory- or IO-bound
+    ///
+    /// Uses default thresholds, see `bottleneck_with`.
+    pub fn bottleneck(&self) -> Bottleneck {
+        self.bottleneck_with(&BottleneckThresholds::default())
+    }
+    /// Classifies the interval as CPU-, memory- or IO-bound
+    ///
+    /// When multiple resources are over the threshold the worst one is
+    /// returned, in order: memory, IO, CPU.
+    pub fn bottleneck_with(&self, thresholds: &BottleneckThresholds)
+        -> Bottleneck
+    {
+        if self.thrashing_indicator >= thresholds.thrashing ||
+            self.memory_swap >= thresholds.memory_swap
+        {
+            Bottleneck::Memory
+        } else if self.disk_read + self.disk_write >= thresholds.disk_bytes ||
+            self.io_read_ops + self.io_write_ops >= thresholds.io_ops
+        {
+            Bottleneck::Io
+        } else if self.process_cpu_usage >= thresholds.cpu_usage {
+            Bottleneck::Cpu
+        } else {
+            Bottleneck::None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use Report;
+    use report::test::{snapshot, meter_with};
+    use super::{Bottleneck, BottleneckThresholds};
+
+    /// Name of the case, update of the idle report and expected result
+    type Case = (&'static str, fn(&mut Report), Bottleneck);
+
+    fn idle_report() -> Report {
+        meter_with(snapshot(100, 0), snapshot(200, 0)).report().unwrap()
+    }
+
+    #[test]
+    fn classify() {
+        let cases: &[Case] = &[
+            ("idle", |_| {}, Bottleneck::None),
+            ("balanced", |r| {
+                r.process_cpu_usage = 60.;
+                r.memory_swap = 16 << 20;
+                r.thrashing_indicator = 0.1;
+                r.disk_read = (10 << 20) as f32;
+                r.io_read_ops = 1000.;
+            }, Bottleneck::None),
+            ("cpu", |r| r.process_cpu_usage = 95., Bottleneck::Cpu),
+            ("parallel cpu", |r| r.process_cpu_usage = 400., Bottleneck::Cpu),
+            ("disk", |r| r.disk_write = (60 << 20) as f32, Bottleneck::Io),
+            ("syscalls", |r| r.io_write_ops = 20000., Bottleneck::Io),
+            ("swap", |r| r.memory_swap = 1 << 30, Bottleneck::Memory),
+            ("thrashing", |r| r.thrashing_indicator = 0.5,
+                Bottleneck::Memory),
+            ("io over cpu", |r| {
+                r.process_cpu_usage = 95.;
+                r.disk_read = (60 << 20) as f32;
+            }, Bottleneck::Io),
+            ("memory over io", |r| {
+                r.disk_read = (60 << 20) as f32;
+                r.thrashing_indicator = 0.5;
+            }, Bottleneck::Memory),
+        ];
+        for &(name, update, expected) in cases {
+            let mut report = idle_report();
+            update(&mut report);
+            assert_eq!(report.bottleneck(), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn custom_thresholds() {
+        let mut report = idle_report();
+        report.process_cpu_usage = 60.;
+        assert_eq!(report.bottleneck(), Bottleneck::None);
+        let thresholds = BottleneckThresholds {
+            cpu_usage: 50.,
+            ..BottleneckThresholds::default()
+        };
+        assert_eq!(report.bottleneck_with(&thresholds), Bottleneck::Cpu);
+    }
+}
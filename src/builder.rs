@@ -0,0 +1,129 @@
+use std::cmp::max;
+use std::collections::VecDeque;
+use std::time::Duration;
+use std::path::{Path, PathBuf};
+
+use {Meter, Error};
+
+
+/// What `Meter::report` returns before there are two scans
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature="serde", derive(Serialize, Deserialize))]
+pub enum WarmupBehavior {
+    /// Return `None` (default)
+    None,
+    /// Return a report for the single available snapshot
+    ///
+    /// Memory usage and other gauges in such report are real, but all
+    /// rates (CPU usage, IO per second and similar) are zero, as they
+    /// are meaningless until the second scan.
+    ZeroReport,
+}
+
+/// Builder for a `Meter` with non-default options
+///
+/// ```rust,no_run
+/// # use std::time::Duration;
+/// let meter = self_meter::MeterBuilder::new(Duration::new(1, 0))
+///     .num_cpus(2)
+///     .include_child_cpu(false)
+///     .build().unwrap();
+/// ```
+///
+/// Defaults are the same as for `Meter::new`, and the default scan interval
+/// is one second, so `MeterBuilder::default().build()` is equivalent to
+/// `Meter::new(Duration::new(1, 0))`.
+#[derive(Debug, Clone)]
+pub struct MeterBuilder {
+    scan_interval: Duration,
+    num_snapshots: usize,
+    num_cpus: Option<usize>,
+    include_child_cpu: bool,
+    warmup_behavior: WarmupBehavior,
+    proc_root: PathBuf,
+}
+
+impl MeterBuilder {
+    /// Create a builder with the scan interval, see `Meter::new`
+    pub fn new(scan_interval: Duration) -> MeterBuilder {
+        MeterBuilder {
+            scan_interval,
+            num_snapshots: 10,
+            num_cpus: None,
+            include_child_cpu: true,
+            warmup_behavior: WarmupBehavior::None,
+            proc_root: PathBuf::from("/proc"),
+        }
+    }
+    /// Set interval between scans, see `Meter::new`
+    pub fn scan_interval(&mut self, value: Duration) -> &mut MeterBuilder {
+        self.scan_interval = value;
+        self
+    }
+    /// Set number of snapshots kept in history, default is `10`
+    ///
+    /// Values smaller than two are clamped to two, as two snapshots are
+    /// needed for a report.
+    pub fn num_snapshots(&mut self, value: usize) -> &mut MeterBuilder {
+        self.num_snapshots = max(value, 2);
+        self
+    }
+    /// Override number of CPUs used to normalize global CPU usage
+    ///
+    /// By default number of CPUs is detected, which over-reports inside
+    /// containers limited to some share of CPUs (unless the limit is a
+    /// cgroup CPU quota, see `Meter::effective_cpus`). Zero is clamped to
+    /// one (and logged with the `log` feature). The cgroup CPU quota is
+    /// ignored if this is set.
+    pub fn num_cpus(&mut self, value: usize) -> &mut MeterBuilder {
+        self.num_cpus = Some(value);
+        self
+    }
+    /// Whether `Report::gross_cpu_usage` includes awaited children
+    ///
+    /// Default is `true`. Supervisors that meter their children separately
+    /// may want to disable it to avoid double accounting, in this case
+    /// `gross_cpu_usage` equals `process_cpu_usage`.
+    pub fn include_child_cpu(&mut self, value: bool) -> &mut MeterBuilder {
+        self.include_child_cpu = value;
+        self
+    }
+    /// Set what is reported when fewer than two scans were done
+    ///
+    /// See `WarmupBehavior` for details.
+    pub fn warmup_behavior(&mut self, value: WarmupBehavior)
+        -> &mut MeterBuilder
+    {
+        self.warmup_behavior = value;
+        self
+    }
+    /// Set where proc filesystem is mounted, default is `/proc`
+    ///
+    /// This allows to read proc of another PID namespace mounted elsewhere,
+    /// or to run meter against a fixture directory in tests. Builder always
+    /// measures `self`, use `Meter::new_for_pid_in_root` for other processes.
+    pub fn proc_root<P: AsRef<Path>>(&mut self, path: P) -> &mut MeterBuilder
+    {
+        self.proc_root = path.as_ref().to_path_buf();
+        self
+    }
+    /// Create a `Meter`
+    pub fn build(&self) -> Result<Meter, Error> {
+        let mut meter = Meter::_new(self.scan_interval, None,
+                                    &self.proc_root)?;
+        if let Some(num_cpus) = self.num_cpus {
+            meter.set_num_cpus(num_cpus);
+        }
+        meter.num_snapshots = self.num_snapshots;
+        meter.snapshots = VecDeque::with_capacity(self.num_snapshots);
+        meter.include_child_cpu = self.include_child_cpu;
+        meter.warmup_behavior = self.warmup_behavior;
+        Ok(meter)
+    }
+}
+
+impl Default for MeterBuilder {
+    fn default() -> MeterBuilder {
+        MeterBuilder::new(Duration::new(1, 0))
+    }
+}
@@ -0,0 +1,259 @@
+use std::cmp::max;
+use std::path::Path;
+#[cfg(target_os="linux")] use std::path::PathBuf;
+
+use {Meter, Pid};
+#[cfg(target_os="linux")] use scan::proc_path;
+
+
+/// Values of cgroup v1 limits which are at least this mean unlimited
+///
+/// Unlimited v1 limit is `PAGE_COUNTER_MAX` in bytes, which is about
+/// `i64::MAX` rounded down to a page
+#[cfg(target_os="linux")]
+const V1_UNLIMITED: u64 = 1 << 62;
+
+impl Meter {
+    /// Returns memory limit of the cgroup (container) in bytes
+    ///
+    /// Limit is read once when meter is created from `memory.max` of
+    /// cgroup v2 or `memory.limit_in_bytes` of cgroup v1, mounted at
+    /// /sys/fs/cgroup (as it is inside containers). When measuring another
+    /// process, its cgroup is looked up in `/proc/<pid>/cgroup` instead.
+    /// Returns `None` if memory is unlimited, there is no cgroup filesystem
+    /// or the cgroup of another process can't be found (and on non-linux).
+    ///
+    /// Use this instead of `Report::system_memory_total` inside containers,
+    /// where the latter is memory of the host.
+    pub fn memory_limit(&self) -> Option<u64> {
+        self.memory_limit
+    }
+    /// Returns number of CPUs the process can effectively use
+    ///
+    /// This is the number of CPUs (detected or set by
+    /// `MeterBuilder::num_cpus`), lowered to the CPU quota of the cgroup if
+    /// there is one, i.e. `quota / period` of `cpu.max` (cgroup v2) or
+    /// `cpu.cfs_quota_us` and `cpu.cfs_period_us` (v1) of the measured
+    /// process' cgroup (see `memory_limit`). Quota is read once
+    /// when meter is created and is ignored if number of CPUs is set
+    /// explicitly in the builder. It may be fractional, e.g. `0.5` for a
+    /// container limited to half a core.
+    ///
+    /// Process' CPU usage and parallelism in the `Report` are bounded by
+    /// this value. `Report::global_cpu_usage` is still for the whole host,
+    /// as the kernel doesn't account idle time per cgroup.
+    pub fn effective_cpus(&self) -> f32 {
+        let num_cpus = max(self.num_cpus, 1) as f32;
+        match self.cpu_quota {
+            Some(quota) => quota.min(num_cpus),
+            None => num_cpus,
+        }
+    }
+}
+
+/// Mount point of the cgroup filesystem
+pub const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// Reads memory limit and CPU quota of the process' cgroup
+///
+/// Limits of `self` are read at `cgroup_root`, which is the process' own
+/// cgroup inside a container. For another pid the cgroup is looked up in
+/// `<proc_root>/<pid>/cgroup`, both limits are `None` if it can't be
+/// resolved (e.g. the process is in another cgroup namespace, or its
+/// cgroup isn't visible under `cgroup_root`).
+#[cfg(target_os="linux")]
+pub fn read_limits(cgroup_root: &Path, proc_root: &Path, pid: Option<Pid>)
+    -> (Option<u64>, Option<f32>)
+{
+    let text = match pid {
+        Some(_) => match read_proc_cgroup(proc_root, pid) {
+            Some(text) => text,
+            None => return (None, None),
+        },
+        None => String::new(),
+    };
+    let (unified, memory, cpu) = if pid.is_some() {
+        (cgroup_path(&text, ""), cgroup_path(&text, "memory"),
+         cgroup_path(&text, "cpu"))
+    } else {
+        (Some(""), Some(""), Some(""))
+    };
+    (read_memory_limit(cgroup_root, unified, memory),
+     read_cpu_quota(cgroup_root, unified, cpu))
+}
+
+#[cfg(not(target_os="linux"))]
+pub fn read_limits(_cgroup_root: &Path, _proc_root: &Path, _pid: Option<Pid>)
+    -> (Option<u64>, Option<f32>)
+{
+    (None, None)
+}
+
+/// Reads /proc/<pid>/cgroup
+#[cfg(target_os="linux")]
+pub fn read_proc_cgroup(proc_root: &Path, pid: Option<Pid>)
+    -> Option<String>
+{
+    let mut path = String::with_capacity(32);
+    proc_path(&mut path, proc_root, pid, "cgroup");
+    read_file(Path::new(&path))
+}
+
+#[cfg(all(not(target_os="linux"), feature="serde"))]
+pub fn read_proc_cgroup(_proc_root: &Path, _pid: Option<Pid>)
+    -> Option<String>
+{
+    None
+}
+
+/// Returns path of the cgroup having `controller` from /proc/<pid>/cgroup
+///
+/// Lines look like `hierarchy-id:controllers:path`, empty `controller`
+/// means the unified (v2) hierarchy. Paths leaving the cgroup namespace
+/// (starting with `/..`) can't be resolved and are skipped.
+#[cfg(target_os="linux")]
+fn cgroup_path<'a>(text: &'a str, controller: &str) -> Option<&'a str> {
+    text.lines().filter_map(|line| {
+        let mut parts = line.splitn(3, ':');
+        let id = parts.next()?;
+        let controllers = parts.next()?;
+        let path = parts.next()?;
+        let matches = if controller.is_empty() {
+            id == "0" && controllers.is_empty()
+        } else {
+            controllers.split(',').any(|c| c == controller)
+        };
+        if matches { Some(path) } else { None }
+    })
+    .find(|path| !path.split('/').any(|part| part == ".."))
+}
+
+/// Returns path of `file` of `cgroup` in the hierarchy mounted at `root`
+#[cfg(target_os="linux")]
+fn cgroup_file(root: &Path, cgroup: &str, file: &str) -> PathBuf {
+    root.join(cgroup.trim_start_matches('/')).join(file)
+}
+
+/// Reads `memory.max` (v2) or `memory.limit_in_bytes` (v1) of the cgroup
+#[cfg(target_os="linux")]
+fn read_memory_limit(root: &Path, unified: Option<&str>, v1: Option<&str>)
+    -> Option<u64>
+{
+    unified.and_then(|path| read_file(&cgroup_file(root, path, "memory.max")))
+        .or_else(|| v1.and_then(|path| read_file(&cgroup_file(
+            &root.join("memory"), path, "memory.limit_in_bytes"))))
+        .and_then(|text| parse_limit(&text))
+}
+
+/// Returns CPU quota of the cgroup in (possibly fractional) cores
+#[cfg(target_os="linux")]
+fn read_cpu_quota(root: &Path, unified: Option<&str>, v1: Option<&str>)
+    -> Option<f32>
+{
+    let cpu_max = unified.and_then(|path| {
+        read_file(&cgroup_file(root, path, "cpu.max"))
+    });
+    if let Some(text) = cpu_max {
+        return parse_cpu_max(&text);
+    }
+    let (root, path) = (root.join("cpu"), v1?);
+    let quota = read_file(&cgroup_file(&root, path, "cpu.cfs_quota_us"))?;
+    let period = read_file(&cgroup_file(&root, path, "cpu.cfs_period_us"))?;
+    parse_cfs_quota(&quota, &period)
+}
+
+#[cfg(target_os="linux")]
+fn read_file(path: &Path) -> Option<String> {
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut buf = String::with_capacity(64);
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut buf))
+        .ok()?;
+    Some(buf)
+}
+
+/// Parses a limit file, returns `None` if it's unlimited or malformed
+///
+/// Cgroup v2 writes `max` for unlimited, v1 writes a huge number
+#[cfg(target_os="linux")]
+fn parse_limit(text: &str) -> Option<u64> {
+    match text.trim() {
+        "max" => None,
+        value => value.parse().ok().filter(|&x| x < V1_UNLIMITED),
+    }
+}
+
+/// Parses `cpu.max` of cgroup v2, which looks like `50000 100000`
+/// (quota and period in microseconds) or `max 100000` if unlimited
+#[cfg(target_os="linux")]
+fn parse_cpu_max(text: &str) -> Option<f32> {
+    let mut parts = text.split_whitespace();
+    let quota = parts.next()?;
+    let period = parts.next().unwrap_or("100000");
+    parse_cfs_quota(quota, period)
+}
+
+/// Parses quota and period of cgroup v1, quota is `-1` if unlimited
+#[cfg(target_os="linux")]
+fn parse_cfs_quota(quota: &str, period: &str) -> Option<f32> {
+    let quota: u64 = quota.trim().parse().ok()?;
+    let period: u64 = period.trim().parse().ok()?;
+    if period == 0 {
+        return None;
+    }
+    Some(quota as f32 / period as f32)
+}
+
+#[cfg(all(test, target_os="linux"))]
+mod test {
+    use std::path::Path;
+
+    use super::{parse_limit, parse_cpu_max, parse_cfs_quota};
+    use super::{cgroup_path, read_limits};
+
+    #[test]
+    fn limits() {
+        assert_eq!(parse_limit("536870912\n"), Some(512 << 20));
+        assert_eq!(parse_limit("max\n"), None);
+        assert_eq!(parse_limit("9223372036854771712\n"), None);
+        assert_eq!(parse_limit(""), None);
+    }
+    #[test]
+    fn cpu_quota() {
+        assert_eq!(parse_cpu_max("50000 100000\n"), Some(0.5));
+        assert_eq!(parse_cpu_max("200000 100000\n"), Some(2.));
+        assert_eq!(parse_cpu_max("max 100000\n"), None);
+        assert_eq!(parse_cfs_quota("150000\n", "100000\n"), Some(1.5));
+        assert_eq!(parse_cfs_quota("-1\n", "100000\n"), None);
+        assert_eq!(parse_cfs_quota("100\n", "0\n"), None);
+    }
+    #[test]
+    fn paths() {
+        let text = "12:memory:/docker/abc\n4:cpu,cpuacct:/docker/abc\n\
+                    0::/../sibling\n";
+        assert_eq!(cgroup_path(text, "memory"), Some("/docker/abc"));
+        assert_eq!(cgroup_path(text, "cpu"), Some("/docker/abc"));
+        assert_eq!(cgroup_path(text, "pids"), None);
+        assert_eq!(cgroup_path(text, ""), None);
+        assert_eq!(cgroup_path("0::/app.slice\n", ""), Some("/app.slice"));
+    }
+    #[test]
+    fn fixture_limits() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures");
+        let cgroup = fixtures.join("cgroup");
+        let proc_root = fixtures.join("proc");
+        // cgroup v2
+        assert_eq!(read_limits(&cgroup, &proc_root, Some(4242)),
+                   (Some(256 << 20), Some(1.5)));
+        // cgroup v1 with an empty unified hierarchy
+        assert_eq!(read_limits(&cgroup, &proc_root, Some(4244)),
+                   (Some(128 << 20), Some(0.5)));
+        // no such process, limits of the meter's own cgroup aren't used
+        assert_eq!(read_limits(&cgroup, &proc_root, Some(4243)),
+                   (None, None));
+        assert_eq!(read_limits(&cgroup, &proc_root, None), (None, None));
+    }
+}
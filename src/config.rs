@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use {Meter, Pid, WarmupBehavior};
+#[cfg(feature="serde")] use serialize;
+
+
+/// Effective configuration of a `Meter`, returned by `Meter::config`
+///
+/// Note: with the `serde` feature this structure implements
+/// `serde::Serialize` so it can be attached to bug reports. It's also
+/// included by `Meter::report_json` when metadata is set with
+/// `Meter::set_metadata`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature="serde", derive(Serialize, Deserialize))]
+pub struct MeterConfig {
+    /// Scan interval configured in constructor
+    #[cfg_attr(feature="serde",
+        serde(serialize_with="serialize::serialize_duration",
+              deserialize_with="serialize::deserialize_duration"))]
+    pub scan_interval: Duration,
+    /// Number of snapshots kept in history
+    pub num_snapshots: usize,
+    /// Number of CPUs used to normalize global CPU usage
+    pub num_cpus: usize,
+    /// Number of CPUs lowered to cgroup CPU quota, see
+    /// `Meter::effective_cpus`
+    pub effective_cpus: f32,
+    /// Process being measured, `None` is the current process
+    pub pid: Option<Pid>,
+    /// Root of the proc filesystem
+    pub proc_root: String,
+    /// Processes tracked with `Meter::track_process`
+    pub tracked_processes: Vec<Pid>,
+    /// Whether /proc/self/smaps is scanned
+    pub memory_regions: bool,
+    /// Whether /proc/self/numa_maps is scanned
+    pub numa_maps: bool,
+    /// Whether /proc/self/fd is listed
+    pub fd_types: bool,
+    /// Whether IO of tracked threads is read
+    pub thread_io: bool,
+    /// Whether scheduler statistics of tracked threads are read
+    pub thread_schedstat: bool,
+    /// Whether `gross_cpu_usage` includes awaited children
+    pub include_child_cpu: bool,
+    /// What is reported before the second scan
+    pub warmup_behavior: WarmupBehavior,
+}
+
+impl Meter {
+    /// Returns effective configuration of the meter
+    pub fn config(&self) -> MeterConfig {
+        let mut tracked_processes = self.processes.keys()
+            .cloned().collect::<Vec<_>>();
+        tracked_processes.sort();
+        MeterConfig {
+            scan_interval: self.scan_interval,
+            num_snapshots: self.num_snapshots,
+            num_cpus: self.num_cpus,
+            effective_cpus: self.effective_cpus(),
+            pid: self.pid,
+            proc_root: self.proc_root.display().to_string(),
+            tracked_processes,
+            memory_regions: self.memory_regions,
+            numa_maps: self.numa_maps,
+            fd_types: self.fd_types,
+            thread_io: self.thread_io,
+            thread_schedstat: self.thread_schedstat,
+            include_child_cpu: self.include_child_cpu,
+            warmup_behavior: self.warmup_behavior,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use {MeterBuilder, WarmupBehavior};
+
+    #[test]
+    fn config() {
+        let mut meter = MeterBuilder::new(Duration::new(2, 0))
+            .num_snapshots(5)
+            .num_cpus(3)
+            .include_child_cpu(false)
+            .build().unwrap();
+        meter.enable_thread_io(true);
+        let config = meter.config();
+        assert_eq!(config.scan_interval, Duration::new(2, 0));
+        assert_eq!(config.num_snapshots, 5);
+        assert_eq!(config.num_cpus, 3);
+        assert_eq!(config.pid, None);
+        assert_eq!(config.proc_root, "/proc");
+        assert!(config.tracked_processes.is_empty());
+        assert!(config.thread_io);
+        assert!(!config.include_child_cpu);
+        assert!(!config.memory_regions);
+        assert_eq!(config.warmup_behavior, WarmupBehavior::None);
+    }
+
+    #[test]
+    #[cfg(feature="serde")]
+    fn report_json() {
+        use std::collections::BTreeMap;
+        use report::test::{snapshot, meter_with};
+
+        let mut meter = meter_with(snapshot(100, 0), snapshot(200, 0));
+        let json = meter.report_json().unwrap();
+        assert!(json.get("config").is_none());
+        assert!(json.get("meta").is_none());
+        let mut meta = BTreeMap::new();
+        meta.insert("service".to_string(), "test".to_string());
+        meter.set_metadata(meta);
+        let json = meter.report_json().unwrap();
+        assert_eq!(json["config"]["num_snapshots"], 10);
+        assert_eq!(json["meta"]["service"], "test");
+        assert!(json.get("memory_rss").is_some());
+        let text = meter.report_json_str().unwrap();
+        assert!(text.contains("\"config\":{"));
+    }
+}
@@ -1,6 +1,7 @@
 use std::fmt;
 
-use {Meter, ThreadReportIter};
+use {Meter, ThreadReportIter, ProcessReportIter, ReportHistoryIter};
+use TrackedThreadsIter;
 
 impl fmt::Debug for Meter {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -8,6 +9,7 @@ impl fmt::Debug for Meter {
         .field("scan_interval", &self.scan_interval)
         .field("snapshots", &self.snapshots.len())
         .field("threads", &self.thread_names.len())
+        .field("processes", &self.processes.len())
         .finish()
     }
 }
@@ -18,3 +20,24 @@ impl<'a> fmt::Debug for ThreadReportIter<'a> {
         .finish()
     }
 }
+
+impl<'a> fmt::Debug for ProcessReportIter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ProcessReportIter")
+        .finish()
+    }
+}
+
+impl<'a> fmt::Debug for ReportHistoryIter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReportHistoryIter")
+        .finish()
+    }
+}
+
+impl<'a> fmt::Debug for TrackedThreadsIter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TrackedThreadsIter")
+        .finish()
+    }
+}
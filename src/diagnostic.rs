@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde_json::Value;
+
+use {Meter, Pid};
+use kernel::{SMAPS_VERSION, NUMA_MAPS_VERSION};
+use cgroup::read_proc_cgroup;
+
+
+impl Meter {
+    /// Returns everything meter knows as a single JSON value
+    ///
+    /// This is intended to be attached to bug reports. Includes the latest
+    /// report, thread reports, raw counters of the latest scan, meter's
+    /// configuration, kernel version, cgroup of the process and which
+    /// optional collectors the kernel supports. Fields which are unknown
+    /// (e.g. there were no scans yet) are `null`.
+    pub fn diagnostic_dump(&self) -> Value {
+        let threads = self.thread_report().map(|iter| {
+            iter.map(|(name, rep)| (name.to_string(), rep))
+                .collect::<BTreeMap<_, _>>()
+        });
+        let counters = self.snapshots.back().map(|snap| json!({
+            "uptime": snap.uptime,
+            "idle_time": snap.idle_time,
+            "stat_idle": snap.stat_idle,
+            "stat_total": snap.stat_total,
+            "user_time": snap.process.user_time,
+            "system_time": snap.process.system_time,
+            "child_user_time": snap.process.child_user_time,
+            "child_system_time": snap.process.child_system_time,
+            "minor_faults": snap.process.minor_faults,
+            "major_faults": snap.process.major_faults,
+            "memory_rss": snap.memory_rss,
+            "memory_virtual": snap.memory_virtual,
+            "memory_virtual_peak": snap.memory_virtual_peak,
+            "memory_rss_hwm": snap.memory_rss_hwm,
+            "memory_swap": snap.memory_swap,
+            "memory_rss_anon": snap.memory_rss_anon,
+            "memory_rss_file": snap.memory_rss_file,
+            "memory_rss_shmem": snap.memory_rss_shmem,
+            "memory_data": snap.memory_data,
+            "memory_stack": snap.memory_stack,
+            "memory_exe": snap.memory_exe,
+            "memory_lib": snap.memory_lib,
+            "read_bytes": snap.read_bytes,
+            "write_bytes": snap.write_bytes,
+            "read_ops": snap.read_ops,
+            "write_ops": snap.write_ops,
+            "read_disk_bytes": snap.read_disk_bytes,
+            "write_disk_bytes": snap.write_disk_bytes,
+            "write_cancelled_bytes": snap.write_cancelled_bytes,
+            "net_rx_bytes": snap.net_rx_bytes,
+            "net_tx_bytes": snap.net_tx_bytes,
+            "net_rx_packets": snap.net_rx_packets,
+            "net_tx_packets": snap.net_tx_packets,
+            "swap_in_pages": snap.swap_in_pages,
+            "system_memory_total": snap.system_memory_total,
+            "system_memory_available": snap.system_memory_available,
+            "system_memory_free": snap.system_memory_free,
+            "tracer_pid": snap.tracer_pid,
+        }));
+        json!({
+            "report": self.report(),
+            "threads": threads,
+            "processes": self.process_reports()
+                .map(|(pid, rep)| (pid.to_string(), rep))
+                .collect::<BTreeMap<_, _>>(),
+            "counters": counters,
+            "extended": self.extended_report(),
+            "config": self.config(),
+            "kernel_version": self.kernel_version(),
+            "cgroup": read_cgroup(&self.proc_root, self.pid),
+            "memory_limit": self.memory_limit(),
+            "capabilities": {
+                "smaps": self.kernel_supports(SMAPS_VERSION),
+                "numa_maps": self.kernel_supports(NUMA_MAPS_VERSION),
+            },
+        })
+    }
+}
+
+/// Returns cgroup path of the process from /proc/<pid>/cgroup under
+/// `proc_root`
+///
+/// The unified (v2) hierarchy is preferred, otherwise the first one is used
+fn read_cgroup(proc_root: &Path, pid: Option<Pid>) -> Option<String> {
+    let buf = read_proc_cgroup(proc_root, pid)?;
+    // lines look like `hierarchy-id:controllers:path`
+    let paths = buf.lines().filter_map(|line| {
+        let mut parts = line.splitn(3, ':');
+        Some((parts.next()?, parts.nth(1)?))
+    });
+    let mut first = None;
+    for (id, path) in paths {
+        if id == "0" {
+            return Some(path.to_string());
+        }
+        first = first.or(Some(path));
+    }
+    first.map(|x| x.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use Meter;
+
+    #[test]
+    fn keys() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        let dump = meter.diagnostic_dump();
+        for key in &["report", "threads", "processes", "counters",
+                     "extended", "config", "kernel_version", "cgroup",
+                     "memory_limit", "capabilities"]
+        {
+            assert!(dump.get(key).is_some(), "no {} key", key);
+        }
+        assert!(dump["report"].is_null());
+        assert!(dump["counters"].is_null());
+        assert!(dump["config"].is_object());
+        assert!(dump["capabilities"]["smaps"].is_boolean());
+        assert!(dump["capabilities"]["numa_maps"].is_boolean());
+
+        meter.scan().unwrap();
+        meter.scan().unwrap();
+        let dump = meter.diagnostic_dump();
+        assert!(dump["report"]["memory_rss"].is_number());
+        assert!(dump["counters"]["uptime"].is_number());
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn fixture_cgroup() {
+        use std::path::Path;
+        use super::read_cgroup;
+
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/proc");
+        assert_eq!(read_cgroup(&root, Some(4242)).unwrap(),
+                   "/app.slice/svc.scope");
+        // unified hierarchy is preferred
+        assert_eq!(read_cgroup(&root, Some(4244)).unwrap(), "/");
+        assert_eq!(read_cgroup(&root, Some(4243)), None);
+    }
+}
@@ -1,5 +1,5 @@
 use std::io;
-use std::num::ParseIntError;
+use std::num::{ParseIntError, ParseFloatError};
 
 use Pid;
 
@@ -28,16 +28,19 @@ quick_error! {
     #[derive(Debug)]
     /// Error reading or parsing /proc/self/stat or /proc/self/task/<TID>/stat
     pub enum StatError {
+        /// Error reading the file
         Io(err: io::Error) {
             description("IO error")
             display("{}", err)
             from()
         }
+        /// Error parsing a number in the file
         ParseInt(e: ParseIntError) {
             description("error parsing int")
             display("error parsing int: {}", e)
             from()
         }
+        /// File has unexpected format
         BadFormat {
             description("bad format")
         }
@@ -61,6 +64,18 @@ quick_error! {
     }
 }
 
+quick_error! {
+    #[derive(Debug)]
+    /// Error parsing /proc/vmstat
+    pub enum VmStatError {
+        ParseInt(e: ParseIntError) {
+            description("error parsing int")
+            display("error parsing int: {}", e)
+            from()
+        }
+    }
+}
+
 quick_error! {
     #[derive(Debug)]
     /// Error reading or parsing /proc/self/status
@@ -84,6 +99,40 @@ quick_error! {
     }
 }
 
+quick_error! {
+    #[derive(Debug)]
+    /// Error parsing /proc/self/net/dev
+    pub enum NetDevError {
+        ParseInt(e: ParseIntError) {
+            description("error parsing int")
+            display("error parsing int: {}", e)
+            from()
+        }
+        BadFormat {
+            description("bad format")
+        }
+    }
+}
+
+quick_error! {
+    #[derive(Debug)]
+    /// Error reading or parsing /proc/loadavg
+    pub enum LoadAvgError {
+        Io(err: io::Error) {
+            description("IO error")
+            display("{}", err)
+            from()
+        }
+        ParseFloat(e: ParseFloatError) {
+            description("error parsing float")
+            display("error parsing float: {}", e)
+            from()
+        }
+        BadFormat {
+            description("bad format")
+        }
+    }
+}
 
 quick_error! {
     #[derive(Debug)]
@@ -101,6 +150,21 @@ quick_error! {
             display("Error reading /proc/self/status: {}", err)
             from()
         }
+        /// Error reading /proc/stat
+        ProcStat(err: StatError) {
+            description("Error reading /proc/stat")
+            display("Error reading /proc/stat: {}", err)
+        }
+        /// Error reading /proc/self/smaps
+        Smaps(err: StatusError) {
+            description("Error reading /proc/self/smaps")
+            display("Error reading /proc/self/smaps: {}", err)
+        }
+        /// Error reading /proc/self/numa_maps
+        NumaMaps(err: StatusError) {
+            description("Error reading /proc/self/numa_maps")
+            display("Error reading /proc/self/numa_maps: {}", err)
+        }
         /// Error reading /proc/self/stat
         Stat(err: StatError) {
             description("Error reading /proc/self/stat")
@@ -111,11 +175,59 @@ quick_error! {
             description("Error reading /proc/self/task/<TID>/stat")
             display("Error reading /proc/self/task/{}/stat: {}", tid, err)
         }
+        /// Error reading /proc/self/task/<TID>/io
+        ThreadIoStat(tid: Pid, err: IoStatError) {
+            description("Error reading /proc/self/task/<TID>/io")
+            display("Error reading /proc/self/task/{}/io: {}", tid, err)
+        }
         /// Error reading IO stats
         IoStat(err: IoStatError) {
             description("Error reading /proc/self/io")
             display("Error reading /proc/self/io: {}", err)
             from()
         }
+        /// Error scanning process tracked by `Meter::track_process`
+        Process(pid: Pid, err: Box<Error>) {
+            description("Error scanning tracked process")
+            display("Error scanning process {}: {}", pid, err)
+        }
+        /// Error parsing /proc/meminfo
+        MemInfo(err: StatusError) {
+            description("Error parsing /proc/meminfo")
+            display("Error parsing /proc/meminfo: {}", err)
+        }
+        /// Error parsing /proc/vmstat
+        VmStat(err: VmStatError) {
+            description("Error parsing /proc/vmstat")
+            display("Error parsing /proc/vmstat: {}", err)
+            from()
+        }
+        /// Error parsing /proc/self/net/dev
+        NetDev(err: NetDevError) {
+            description("Error parsing /proc/self/net/dev")
+            display("Error parsing /proc/self/net/dev: {}", err)
+            from()
+        }
+        /// Error counting entries of /proc/self/fd
+        FdCount(err: io::Error) {
+            description("Error counting entries of /proc/self/fd")
+            display("Error counting entries of /proc/self/fd: {}", err)
+        }
+        /// Error listing /proc/self/fd
+        FdTypes(err: io::Error) {
+            description("Error listing /proc/self/fd")
+            display("Error listing /proc/self/fd: {}", err)
+        }
+        /// Error reading /proc/loadavg
+        LoadAvg(err: LoadAvgError) {
+            description("Error reading /proc/loadavg")
+            display("Error reading /proc/loadavg: {}", err)
+            from()
+        }
+        /// Error listing /proc/self/task
+        ThreadList(err: io::Error) {
+            description("Error listing /proc/self/task")
+            display("Error listing /proc/self/task: {}", err)
+        }
     }
 }
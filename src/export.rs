@@ -0,0 +1,406 @@
+use std::collections::HashMap;
+use std::time::{Instant, SystemTime};
+
+use {Meter, Pid, Snapshot, ThreadInfo, CoreTimes, RegionKind, FdKind};
+#[cfg(feature="serde")] use serialize;
+
+
+/// Raw counters of a thread (or the whole process) in `SnapshotData`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature="serde", derive(Serialize, Deserialize))]
+pub struct ThreadData {
+    /// Time spent in user mode, in clock ticks
+    pub user_time: u64,
+    /// Time spent in kernel mode, in clock ticks
+    pub system_time: u64,
+    /// User time of awaited children, in clock ticks
+    pub child_user_time: u64,
+    /// Kernel time of awaited children, in clock ticks
+    pub child_system_time: u64,
+    /// Page faults not requiring IO
+    pub minor_faults: u64,
+    /// Page faults requiring IO
+    pub major_faults: u64,
+    /// Bytes read, only if thread IO is enabled
+    pub read_bytes: u64,
+    /// Bytes written, only if thread IO is enabled
+    pub write_bytes: u64,
+    /// Start time after boot, in clock ticks
+    pub start_time: u64,
+    /// Nanoseconds on CPU, only if schedstat is enabled
+    pub run_time_ns: u64,
+    /// Nanoseconds waiting in runqueue, only if schedstat is enabled
+    pub wait_time_ns: u64,
+    /// CPU number the thread was last executed on
+    pub processor: u32,
+}
+
+/// Jiffies of a single core in `SnapshotData`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature="serde", derive(Serialize, Deserialize))]
+pub struct CoreData {
+    /// Core number
+    pub id: u32,
+    /// Idle plus iowait jiffies
+    pub idle: u64,
+    /// Sum of all jiffies
+    pub total: u64,
+}
+
+/// Raw counters of a single scan, returned by `Meter::export_snapshots`
+///
+/// Fields mirror what is read from /proc, without any processing. With
+/// the `serde` feature this structure can be serialized, the timestamp
+/// is stored as milliseconds since the unix epoch.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature="serde", derive(Serialize, Deserialize))]
+pub struct SnapshotData {
+    /// Wall clock time of the scan
+    #[cfg_attr(feature="serde",
+        serde(serialize_with="serialize::serialize_timestamp",
+              deserialize_with="serialize::deserialize_timestamp"))]
+    pub timestamp: SystemTime,
+    /// System uptime in centiseconds
+    pub uptime: u64,
+    /// System idle time in centiseconds (sum over cores)
+    pub idle_time: u64,
+    /// Idle plus iowait jiffies of the `cpu` line of /proc/stat
+    pub stat_idle: u64,
+    /// Sum of all jiffies of the `cpu` line of /proc/stat
+    pub stat_total: u64,
+    /// Iowait jiffies of /proc/stat
+    pub stat_iowait: u64,
+    /// Steal jiffies of /proc/stat
+    pub stat_steal: u64,
+    /// Guest (including guest_nice) jiffies of /proc/stat
+    pub stat_guest: u64,
+    /// Per-core jiffies of /proc/stat, only online cores are listed
+    pub cores: Vec<CoreData>,
+    /// Counters of the whole process
+    pub process: ThreadData,
+    /// Resident memory in bytes
+    pub memory_rss: u64,
+    /// Virtual memory in bytes
+    pub memory_virtual: u64,
+    /// Peak virtual memory in bytes
+    pub memory_virtual_peak: u64,
+    /// Peak resident memory tracked by kernel
+    pub memory_rss_hwm: u64,
+    /// Swapped out memory in bytes
+    pub memory_swap: u64,
+    /// Resident anonymous memory in bytes
+    pub memory_rss_anon: u64,
+    /// Resident file mappings in bytes
+    pub memory_rss_file: u64,
+    /// Resident shared memory in bytes
+    pub memory_rss_shmem: u64,
+    /// Size of data segment in bytes
+    pub memory_data: u64,
+    /// Size of stack in bytes
+    pub memory_stack: u64,
+    /// Size of text segment in bytes
+    pub memory_exe: u64,
+    /// Size of shared libraries in bytes
+    pub memory_lib: u64,
+    /// Bytes read (total)
+    pub read_bytes: u64,
+    /// Bytes written (total)
+    pub write_bytes: u64,
+    /// Read syscalls
+    pub read_ops: u64,
+    /// Write syscalls
+    pub write_ops: u64,
+    /// Bytes read from block-backed filesystems
+    pub read_disk_bytes: u64,
+    /// Bytes written to block-backed filesystems
+    pub write_disk_bytes: u64,
+    /// Bytes of cancelled writes
+    pub write_cancelled_bytes: u64,
+    /// Bytes received over the network
+    pub net_rx_bytes: u64,
+    /// Bytes sent over the network
+    pub net_tx_bytes: u64,
+    /// Packets received over the network
+    pub net_rx_packets: u64,
+    /// Packets sent over the network
+    pub net_tx_packets: u64,
+    /// Pages swapped in system-wide
+    pub swap_in_pages: u64,
+    /// Total system memory in bytes
+    pub system_memory_total: u64,
+    /// Available system memory in bytes
+    pub system_memory_available: u64,
+    /// Free system memory in bytes
+    pub system_memory_free: u64,
+    /// Resident memory by kind of mapping (opt-in)
+    pub memory_by_region: HashMap<RegionKind, u64>,
+    /// Resident memory by NUMA node (opt-in)
+    pub memory_by_numa_node: HashMap<u32, u64>,
+    /// Open file descriptors by kind (opt-in)
+    pub fds_by_type: HashMap<FdKind, u32>,
+    /// Number of threads in the process
+    pub num_threads: u32,
+    /// Number of open file descriptors
+    pub fd_count: u32,
+    /// Voluntary context switches
+    pub voluntary_ctxt_switches: u64,
+    /// Involuntary context switches
+    pub nonvoluntary_ctxt_switches: u64,
+    /// Pid of the debugger, zero if none
+    pub tracer_pid: Pid,
+    /// Counters of tracked threads by thread id
+    pub threads: HashMap<Pid, ThreadData>,
+}
+
+impl ThreadData {
+    fn from_info(info: &ThreadInfo) -> ThreadData {
+        ThreadData {
+            user_time: info.user_time,
+            system_time: info.system_time,
+            child_user_time: info.child_user_time,
+            child_system_time: info.child_system_time,
+            minor_faults: info.minor_faults,
+            major_faults: info.major_faults,
+            read_bytes: info.read_bytes,
+            write_bytes: info.write_bytes,
+            start_time: info.start_time,
+            run_time_ns: info.run_time_ns,
+            wait_time_ns: info.wait_time_ns,
+            processor: info.processor,
+        }
+    }
+    fn to_info(&self) -> ThreadInfo {
+        ThreadInfo {
+            user_time: self.user_time,
+            system_time: self.system_time,
+            child_user_time: self.child_user_time,
+            child_system_time: self.child_system_time,
+            minor_faults: self.minor_faults,
+            major_faults: self.major_faults,
+            read_bytes: self.read_bytes,
+            write_bytes: self.write_bytes,
+            start_time: self.start_time,
+            run_time_ns: self.run_time_ns,
+            wait_time_ns: self.wait_time_ns,
+            processor: self.processor,
+        }
+    }
+}
+
+impl SnapshotData {
+    fn from_snapshot(snap: &Snapshot) -> SnapshotData {
+        SnapshotData {
+            timestamp: snap.timestamp,
+            uptime: snap.uptime,
+            idle_time: snap.idle_time,
+            stat_idle: snap.stat_idle,
+            stat_total: snap.stat_total,
+            stat_iowait: snap.stat_iowait,
+            stat_steal: snap.stat_steal,
+            stat_guest: snap.stat_guest,
+            cores: snap.cores.iter().map(|core| CoreData {
+                id: core.id,
+                idle: core.idle,
+                total: core.total,
+            }).collect(),
+            process: ThreadData::from_info(&snap.process),
+            memory_rss: snap.memory_rss,
+            memory_virtual: snap.memory_virtual,
+            memory_virtual_peak: snap.memory_virtual_peak,
+            memory_rss_hwm: snap.memory_rss_hwm,
+            memory_swap: snap.memory_swap,
+            memory_rss_anon: snap.memory_rss_anon,
+            memory_rss_file: snap.memory_rss_file,
+            memory_rss_shmem: snap.memory_rss_shmem,
+            memory_data: snap.memory_data,
+            memory_stack: snap.memory_stack,
+            memory_exe: snap.memory_exe,
+            memory_lib: snap.memory_lib,
+            read_bytes: snap.read_bytes,
+            write_bytes: snap.write_bytes,
+            read_ops: snap.read_ops,
+            write_ops: snap.write_ops,
+            read_disk_bytes: snap.read_disk_bytes,
+            write_disk_bytes: snap.write_disk_bytes,
+            write_cancelled_bytes: snap.write_cancelled_bytes,
+            net_rx_bytes: snap.net_rx_bytes,
+            net_tx_bytes: snap.net_tx_bytes,
+            net_rx_packets: snap.net_rx_packets,
+            net_tx_packets: snap.net_tx_packets,
+            swap_in_pages: snap.swap_in_pages,
+            system_memory_total: snap.system_memory_total,
+            system_memory_available: snap.system_memory_available,
+            system_memory_free: snap.system_memory_free,
+            memory_by_region: snap.memory_by_region.clone(),
+            memory_by_numa_node: snap.memory_by_numa_node.clone(),
+            fds_by_type: snap.fds_by_type.clone(),
+            num_threads: snap.num_threads,
+            fd_count: snap.fd_count,
+            voluntary_ctxt_switches: snap.voluntary_ctxt_switches,
+            nonvoluntary_ctxt_switches: snap.nonvoluntary_ctxt_switches,
+            tracer_pid: snap.tracer_pid,
+            threads: snap.threads.iter()
+                .map(|(&tid, info)| (tid, ThreadData::from_info(info)))
+                .collect(),
+        }
+    }
+    fn into_snapshot(self, instant: Instant) -> Snapshot {
+        Snapshot {
+            timestamp: self.timestamp,
+            instant,
+            uptime: self.uptime,
+            idle_time: self.idle_time,
+            stat_idle: self.stat_idle,
+            stat_total: self.stat_total,
+            stat_iowait: self.stat_iowait,
+            stat_steal: self.stat_steal,
+            stat_guest: self.stat_guest,
+            cores: self.cores.iter().map(|core| CoreTimes {
+                id: core.id,
+                idle: core.idle,
+                total: core.total,
+            }).collect(),
+            process: self.process.to_info(),
+            memory_rss: self.memory_rss,
+            memory_virtual: self.memory_virtual,
+            memory_virtual_peak: self.memory_virtual_peak,
+            memory_rss_hwm: self.memory_rss_hwm,
+            memory_swap: self.memory_swap,
+            memory_rss_anon: self.memory_rss_anon,
+            memory_rss_file: self.memory_rss_file,
+            memory_rss_shmem: self.memory_rss_shmem,
+            memory_data: self.memory_data,
+            memory_stack: self.memory_stack,
+            memory_exe: self.memory_exe,
+            memory_lib: self.memory_lib,
+            read_bytes: self.read_bytes,
+            write_bytes: self.write_bytes,
+            read_ops: self.read_ops,
+            write_ops: self.write_ops,
+            read_disk_bytes: self.read_disk_bytes,
+            write_disk_bytes: self.write_disk_bytes,
+            write_cancelled_bytes: self.write_cancelled_bytes,
+            net_rx_bytes: self.net_rx_bytes,
+            net_tx_bytes: self.net_tx_bytes,
+            net_rx_packets: self.net_rx_packets,
+            net_tx_packets: self.net_tx_packets,
+            swap_in_pages: self.swap_in_pages,
+            system_memory_total: self.system_memory_total,
+            system_memory_available: self.system_memory_available,
+            system_memory_free: self.system_memory_free,
+            memory_by_region: self.memory_by_region,
+            memory_by_numa_node: self.memory_by_numa_node,
+            fds_by_type: self.fds_by_type,
+            num_threads: self.num_threads,
+            fd_count: self.fd_count,
+            voluntary_ctxt_switches: self.voluntary_ctxt_switches,
+            nonvoluntary_ctxt_switches: self.nonvoluntary_ctxt_switches,
+            tracer_pid: self.tracer_pid,
+            threads: self.threads.iter()
+                .map(|(&tid, data)| (tid, data.to_info()))
+                .collect(),
+        }
+    }
+}
+
+impl Meter {
+    /// Returns raw counters of all snapshots kept in history
+    ///
+    /// Snapshots are ordered oldest to newest. This allows to store data
+    /// collected by a live process and analyze it elsewhere with
+    /// `import_snapshots()`.
+    pub fn export_snapshots(&self) -> Vec<SnapshotData> {
+        self.snapshots.iter().map(SnapshotData::from_snapshot).collect()
+    }
+    /// Replace history with snapshots returned by `export_snapshots()`
+    ///
+    /// Snapshots must be ordered oldest to newest, only the newest
+    /// `num_snapshots` are kept. After that `report()`, `report_over()`
+    /// and other reports are computed from the imported data. Thread
+    /// reports need threads to be tracked with `track_thread()` by the
+    /// same thread ids. Smoothing state of `smoothed_report()` is reset.
+    ///
+    /// Note: the next `scan()` appends a snapshot of the current process
+    /// to the history, so it's better to use a separate meter for
+    /// analysis.
+    pub fn import_snapshots(&mut self, data: Vec<SnapshotData>) {
+        let skip = data.len().saturating_sub(self.num_snapshots);
+        // monotonic time can't be restored, so it's derived from timestamps
+        // relative to the newest snapshot
+        let now = Instant::now();
+        let newest = data.last().map(|snap| snap.timestamp);
+        self.snapshots.clear();
+        for snap in data.into_iter().skip(skip) {
+            let age = newest
+                .and_then(|newest| newest.duration_since(snap.timestamp).ok())
+                .unwrap_or_default();
+            let instant = now.checked_sub(age).unwrap_or(now);
+            self.snapshots.push_back(snap.into_snapshot(instant));
+        }
+        self.reset_smoothing();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use {Meter, Snapshot};
+
+    fn meter() -> Meter {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        for i in 1..4 {
+            let mut snap = Snapshot::new(&HashMap::new());
+            snap.timestamp = UNIX_EPOCH + Duration::new(1_000_000 + i, 0);
+            snap.uptime = i * 100;
+            snap.process.user_time = i * 25;
+            snap.memory_rss = i << 20;
+            snap.read_bytes = i * 1000;
+            meter.snapshots.push_back(snap);
+        }
+        meter
+    }
+
+    #[test]
+    fn round_trip() {
+        let meter = meter();
+        let data = meter.export_snapshots();
+        assert_eq!(data.len(), 3);
+        assert_eq!(data[2].memory_rss, 3 << 20);
+
+        let mut offline = Meter::new(Duration::new(1, 0)).unwrap();
+        offline.import_snapshots(data.clone());
+        assert_eq!(offline.export_snapshots(), data);
+        let live = meter.report().unwrap();
+        let report = offline.report().unwrap();
+        assert_eq!(report.timestamp, live.timestamp);
+        assert_eq!(report.process_cpu_usage, live.process_cpu_usage);
+        assert_eq!(report.memory_rss, live.memory_rss);
+        assert_eq!(report.io_read, live.io_read);
+        let over = offline.report_over(Duration::new(2, 0)).unwrap();
+        assert_eq!(over.duration, Duration::new(2, 0));
+    }
+
+    #[test]
+    fn import_keeps_newest() {
+        let data = meter().export_snapshots();
+        let mut offline = Meter::new(Duration::new(1, 0)).unwrap();
+        offline.num_snapshots = 2;
+        offline.import_snapshots(data);
+        assert_eq!(offline.snapshot_count(), 2);
+        assert_eq!(offline.raw_uptime(), Some((300, 0)));
+    }
+
+    #[test]
+    #[cfg(feature="serde")]
+    fn json() {
+        use serde_json;
+        use SnapshotData;
+
+        let data = meter().export_snapshots();
+        let json = serde_json::to_string(&data).unwrap();
+        let decoded: Vec<SnapshotData> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, data);
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use Meter;
+use kernel::{SMAPS_VERSION, NUMA_MAPS_VERSION};
+
+
+/// Kind of memory mapping, used as a key of `memory_by_region`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature="serde", derive(Serialize, Deserialize))]
+pub enum RegionKind {
+    /// Executable mappings (code of the binary and libraries)
+    Executable,
+    /// Read-only non-executable mappings (constants, read-only files)
+    ReadOnlyData,
+    /// Writable mappings except heap and stack (including anonymous mmap)
+    WritableData,
+    /// Stacks of the threads
+    Stack,
+    /// Heap (the `brk` area)
+    Heap,
+}
+
+/// Kind of file descriptor, used as a key of `fds_by_type`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature="serde", derive(Serialize, Deserialize))]
+pub enum FdKind {
+    /// Regular files, directories and devices (anything having a path)
+    File,
+    /// Sockets of any family
+    Socket,
+    /// Pipes and FIFOs
+    Pipe,
+    /// Epoll instances
+    Epoll,
+    /// Eventfd
+    EventFd,
+    /// Other `anon_inode` descriptors (timerfd, signalfd, inotify...)
+    AnonInode,
+    /// Anything else
+    Other,
+}
+
+/// Report of opt-in collectors returned by `Meter::extended_report`
+///
+/// These collectors are expensive, so each field is `None` unless
+/// respective collector is enabled.
+#[derive(Debug)]
+#[cfg_attr(feature="serde", derive(Serialize))]
+pub struct ExtendedReport {
+    /// Resident memory by kind of mapping, from /proc/self/smaps
+    ///
+    /// Enabled by `Meter::enable_memory_regions`
+    pub memory_by_region: Option<HashMap<RegionKind, u64>>,
+    /// Resident memory in bytes by NUMA node, from /proc/self/numa_maps
+    ///
+    /// Enabled by `Meter::enable_numa_maps`
+    pub memory_by_numa_node: Option<HashMap<u32, u64>>,
+    /// Number of open file descriptors by kind, from /proc/self/fd
+    ///
+    /// Enabled by `Meter::enable_fd_types`
+    pub fds_by_type: Option<HashMap<FdKind, u32>>,
+}
+
+impl Meter {
+    /// Enable scanning /proc/self/smaps to get RSS by kind of mapping
+    ///
+    /// Note: smaps can be huge for processes with lots of mappings, so
+    /// scanning it is the most expensive part of the `scan()`.
+    ///
+    /// Stays disabled if `kernel_version()` is older than 2.6.14.
+    pub fn enable_memory_regions(&mut self, enabled: bool) {
+        self.memory_regions = enabled && self.kernel_supports(SMAPS_VERSION);
+    }
+    /// Enable scanning /proc/self/numa_maps to get RSS by NUMA node
+    ///
+    /// This is the only way to see placement of process' memory on NUMA
+    /// systems. Like smaps, numa_maps has a line per mapping and is
+    /// expensive to read.
+    ///
+    /// Stays disabled if `kernel_version()` is older than 2.6.14.
+    pub fn enable_numa_maps(&mut self, enabled: bool) {
+        self.numa_maps = enabled && self.kernel_supports(NUMA_MAPS_VERSION);
+    }
+    /// Enable listing /proc/self/fd to count descriptors by kind
+    ///
+    /// This needs a `readlink` call per descriptor, so it's expensive for
+    /// processes with many descriptors. But it allows to find out which
+    /// kind of descriptor is leaking.
+    pub fn enable_fd_types(&mut self, enabled: bool) {
+        self.fd_types = enabled;
+    }
+    /// Get report of the opt-in collectors from the latest scan
+    ///
+    /// Returns `None` if there were no scans yet
+    pub fn extended_report(&self) -> Option<ExtendedReport> {
+        let last = self.snapshots.back()?;
+        Some(ExtendedReport {
+            memory_by_region: if self.memory_regions {
+                Some(last.memory_by_region.clone())
+            } else {
+                None
+            },
+            memory_by_numa_node: if self.numa_maps {
+                Some(last.memory_by_numa_node.clone())
+            } else {
+                None
+            },
+            fds_by_type: if self.fd_types {
+                Some(last.fds_by_type.clone())
+            } else {
+                None
+            },
+        })
+    }
+}
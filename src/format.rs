@@ -0,0 +1,262 @@
+use std::fmt::Write;
+use std::time::{SystemTime, Duration, UNIX_EPOCH};
+
+use {Meter, Report};
+
+
+/// Milliseconds since the unix epoch, timestamps before it are zero
+pub fn tstamp_to_ms(tm: SystemTime) -> u64 {
+    let ts = tm.duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::new(0, 0));
+    duration_to_ms(ts)
+}
+
+pub fn duration_to_ms(dur: Duration) -> u64 {
+    dur.as_secs().saturating_mul(1000)
+        .saturating_add(dur.subsec_millis() as u64)
+}
+
+const UNITS: &[char] = &['B', 'K', 'M', 'G', 'T', 'P'];
+
+/// Writes size in bytes using the smallest unit where number is below
+/// 1000, e.g. `512B`, `45M` (with `decimals=0`) or `1.2G` (`decimals=1`)
+fn write_size(buf: &mut String, bytes: f64, decimals: usize) {
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1000. && unit < UNITS.len() - 1 {
+        value /= 1024.;
+        unit += 1;
+    }
+    write!(buf, "{:.*}{}", decimals, value, UNITS[unit]).unwrap();
+}
+
+impl Report {
+    /// Returns compact one-line summary suitable for a status bar
+    ///
+    /// Format is `C<cpu>% M<rss> R<disk_read>/s W<disk_write>/s`,
+    /// for example `C12% M45M R1.2M/s W0.0B/s`, where:
+    ///
+    /// * `<cpu>` is `process_cpu_usage` rounded to an integer
+    /// * `<rss>` is `memory_rss` with no decimals
+    /// * `<disk_read>` and `<disk_write>` have a single decimal
+    ///
+    /// Sizes are suffixed with one of `B`, `K`, `M`, `G`, `T`, `P`
+    /// (powers of 1024), the smallest unit which keeps the number
+    /// below 1000 is used.
+    pub fn status_line(&self) -> String {
+        let mut buf = String::with_capacity(32);
+        write!(buf, "C{:.0}% M", self.process_cpu_usage).unwrap();
+        write_size(&mut buf, self.memory_rss as f64, 0);
+        buf.push_str(" R");
+        write_size(&mut buf, self.disk_read as f64, 1);
+        buf.push_str("/s W");
+        write_size(&mut buf, self.disk_write as f64, 1);
+        buf.push_str("/s");
+        buf
+    }
+}
+
+const CSV_HEADER: &str = "timestamp,duration,start_time,system_uptime,\
+    global_cpu_usage,global_iowait,global_steal,global_guest,\
+    process_cpu_usage,process_cpu_peak,process_user_cpu,\
+    process_system_cpu,kernel_user_ratio,gross_cpu_usage,parallelism,\
+    memory_rss,memory_virtual,memory_swap,memory_rss_anon,\
+    memory_rss_file,memory_rss_shmem,memory_data,memory_stack,\
+    memory_exe,memory_lib,memory_rss_peak,memory_rss_hwm,\
+    memory_virtual_peak,memory_swap_peak,system_memory_total,\
+    system_memory_available,system_memory_free,num_threads,\
+    num_threads_peak,fd_count,fd_count_peak,voluntary_ctxt_switches,\
+    involuntary_ctxt_switches,disk_read,disk_write,disk_cancelled,\
+    io_read,io_write,io_read_ops,io_write_ops,\
+    io_read_total_since_baseline,io_write_total_since_baseline,\
+    disk_read_total_since_baseline,disk_write_total_since_baseline,\
+    io_read_write_ratio,cache_hit_ratio,minor_faults,major_faults,\
+    thrashing_indicator,is_traced,suspended_during_interval,\
+    net_read,net_write,net_read_packets,net_write_packets,memory_limit";
+
+impl Report {
+    /// Returns header line for `csv_row()` (without trailing newline)
+    ///
+    /// Columns are named after the fields of the `Report`. Non-numeric
+    /// fields (`global_cpu_source`) are omitted.
+    ///
+    /// The format is stable within a major version of the library:
+    /// columns may only be appended at the end in a minor release.
+    pub fn csv_header() -> &'static str {
+        CSV_HEADER
+    }
+    /// Returns report as a comma-separated line matching `csv_header()`
+    ///
+    /// Timestamps are milliseconds since the unix epoch, durations are
+    /// milliseconds, booleans are `0` or `1`, and `None` values are empty
+    /// cells. No trailing newline is added.
+    pub fn csv_row(&self) -> String {
+        let mut buf = String::with_capacity(512);
+        write!(buf, "{},{},{},{}",
+            tstamp_to_ms(self.timestamp), duration_to_ms(self.duration),
+            tstamp_to_ms(self.start_time),
+            duration_to_ms(self.system_uptime)).unwrap();
+        for value in &[self.global_cpu_usage, self.global_iowait,
+                       self.global_steal, self.global_guest,
+                       self.process_cpu_usage, self.process_cpu_peak,
+                       self.process_user_cpu, self.process_system_cpu,
+                       self.kernel_user_ratio, self.gross_cpu_usage,
+                       self.parallelism]
+        {
+            write!(buf, ",{}", value).unwrap();
+        }
+        for value in &[self.memory_rss, self.memory_virtual,
+                       self.memory_swap, self.memory_rss_anon,
+                       self.memory_rss_file, self.memory_rss_shmem,
+                       self.memory_data, self.memory_stack,
+                       self.memory_exe, self.memory_lib,
+                       self.memory_rss_peak, self.memory_rss_hwm,
+                       self.memory_virtual_peak, self.memory_swap_peak,
+                       self.system_memory_total,
+                       self.system_memory_available,
+                       self.system_memory_free]
+        {
+            write!(buf, ",{}", value).unwrap();
+        }
+        for value in &[self.num_threads, self.num_threads_peak,
+                       self.fd_count, self.fd_count_peak]
+        {
+            write!(buf, ",{}", value).unwrap();
+        }
+        for value in &[self.voluntary_ctxt_switches,
+                       self.involuntary_ctxt_switches,
+                       self.disk_read, self.disk_write, self.disk_cancelled,
+                       self.io_read, self.io_write,
+                       self.io_read_ops, self.io_write_ops]
+        {
+            write!(buf, ",{}", value).unwrap();
+        }
+        for value in &[self.io_read_total_since_baseline,
+                       self.io_write_total_since_baseline,
+                       self.disk_read_total_since_baseline,
+                       self.disk_write_total_since_baseline]
+        {
+            write!(buf, ",{}", value).unwrap();
+        }
+        for value in &[self.io_read_write_ratio, self.cache_hit_ratio] {
+            buf.push(',');
+            if let Some(value) = value {
+                write!(buf, "{}", value).unwrap();
+            }
+        }
+        for value in &[self.minor_faults, self.major_faults,
+                       self.thrashing_indicator]
+        {
+            write!(buf, ",{}", value).unwrap();
+        }
+        for value in &[self.is_traced, self.suspended_during_interval] {
+            write!(buf, ",{}", *value as u8).unwrap();
+        }
+        for value in &[self.net_read, self.net_write,
+                       self.net_read_packets, self.net_write_packets]
+        {
+            write!(buf, ",{}", value).unwrap();
+        }
+        buf.push(',');
+        if let Some(limit) = self.memory_limit {
+            write!(buf, "{}", limit).unwrap();
+        }
+        buf
+    }
+}
+
+/// Writes thread name, replacing characters that are special in folded
+/// stacks format
+fn write_frame(buf: &mut String, name: &str) {
+    buf.extend(name.chars()
+        .map(|c| if c == ';' || c.is_whitespace() { '_' } else { c }));
+}
+
+impl Meter {
+    /// Returns CPU time of the threads in folded stacks format
+    ///
+    /// Each tracked thread is a line `<process>;<thread_name> <ms>`, where
+    /// `<process>` is the pid (or `self`) and `<ms>` is milliseconds
+    /// of CPU time (user + system) the thread used during the last scan
+    /// interval. Lines are sorted by thread name. The output can be fed to
+    /// flamegraph tools to get thread-level (not stack-level) graph.
+    ///
+    /// Returns `None` under the same conditions as `thread_report()`.
+    pub fn report_folded(&self) -> Option<String> {
+        if self.snapshots.len() < 2 {
+            return None;
+        }
+        let n = self.snapshots.len();
+        let last = &self.snapshots[n-1];
+        let prev = &self.snapshots[n-2];
+        let mut threads = self.thread_names.iter().collect::<Vec<_>>();
+        threads.sort_by(|a, b| a.1.cmp(b.1));
+        let mut buf = String::new();
+        for (tid, name) in threads {
+            let (lth, pth) = match (last.threads.get(tid),
+                                    prev.threads.get(tid)) {
+                (Some(lth), Some(pth)) => (lth, pth),
+                _ => continue,  // not enough stats for a thread yet
+            };
+            let ticks = lth.user_time.saturating_add(lth.system_time)
+                .saturating_sub(pth.user_time.saturating_add(pth.system_time));
+            match self.pid {
+                Some(pid) => write!(buf, "{};", pid).unwrap(),
+                None => buf.push_str("self;"),
+            }
+            write_frame(&mut buf, name);
+            // ticks are centiseconds
+            writeln!(buf, " {}", ticks.saturating_mul(10)).unwrap();
+        }
+        Some(buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use Report;
+    use report::test::{snapshot, meter_with};
+    use super::write_size;
+
+    fn size(bytes: f64, decimals: usize) -> String {
+        let mut buf = String::new();
+        write_size(&mut buf, bytes, decimals);
+        buf
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(size(0., 1), "0.0B");
+        assert_eq!(size(512., 0), "512B");
+        assert_eq!(size(47185920., 0), "45M");
+        assert_eq!(size(1258291.2, 1), "1.2M");
+        assert_eq!(size(1000., 1), "1.0K");
+    }
+
+    #[test]
+    fn csv() {
+        let mut last = snapshot(200, 0);
+        last.memory_rss = 4096;
+        let report = meter_with(snapshot(100, 0), last).report().unwrap();
+        let header = Report::csv_header();
+        let row = report.csv_row();
+        assert!(!header.contains(' '));
+        assert_eq!(header.split(',').count(), row.split(',').count());
+        let columns = header.split(',').zip(row.split(','))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(columns["memory_rss"], "4096");
+        assert_eq!(columns["is_traced"], "0");
+    }
+
+    #[test]
+    fn csv_before_epoch() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let meter = meter_with(snapshot(100, 0), snapshot(200, 0));
+        let mut report = meter.report().unwrap();
+        report.timestamp = UNIX_EPOCH - Duration::new(1, 0);
+        assert!(report.csv_row().starts_with("0,"));
+    }
+}
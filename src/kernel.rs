@@ -0,0 +1,76 @@
+use std::path::Path;
+
+use Meter;
+
+
+/// Kernel version as `(major, minor, patch)`
+pub type KernelVersion = (u32, u32, u32);
+
+/// First kernel having /proc/self/smaps
+pub const SMAPS_VERSION: KernelVersion = (2, 6, 14);
+/// First kernel having /proc/self/numa_maps
+pub const NUMA_MAPS_VERSION: KernelVersion = (2, 6, 14);
+
+impl Meter {
+    /// Returns version of the running kernel (from /proc/sys/kernel/osrelease)
+    ///
+    /// Version is read once when meter is created. It's used to disable
+    /// collectors which the kernel doesn't support instead of failing on
+    /// each scan. Returns `None` if version is unknown (and on non-linux).
+    pub fn kernel_version(&self) -> Option<KernelVersion> {
+        self.kernel_version
+    }
+    /// Returns false only if kernel is known to be older than `version`
+    pub(crate) fn kernel_supports(&self, version: KernelVersion) -> bool {
+        self.kernel_version.map(|v| v >= version).unwrap_or(true)
+    }
+}
+
+#[cfg(target_os="linux")]
+pub fn read_kernel_version(proc_root: &Path) -> Option<KernelVersion> {
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut buf = String::with_capacity(64);
+    File::open(proc_root.join("sys/kernel/osrelease"))
+        .and_then(|mut f| f.read_to_string(&mut buf))
+        .ok()?;
+    parse_kernel_version(&buf)
+}
+
+#[cfg(not(target_os="linux"))]
+pub fn read_kernel_version(_proc_root: &Path) -> Option<KernelVersion> {
+    None
+}
+
+/// Parses release like `4.19.0-6-amd64` or `5.4`
+#[cfg(target_os="linux")]
+fn parse_kernel_version(release: &str) -> Option<KernelVersion> {
+    let end = release.find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(release.len());
+    let mut parts = release[..end].split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    let patch = match parts.next() {
+        Some(x) => x.parse().ok()?,
+        None => 0,
+    };
+    Some((major, minor, patch))
+}
+
+#[cfg(all(test, target_os="linux"))]
+mod test {
+    use super::parse_kernel_version;
+
+    #[test]
+    fn versions() {
+        assert_eq!(parse_kernel_version("4.19.0-6-amd64\n"),
+                   Some((4, 19, 0)));
+        assert_eq!(parse_kernel_version("6.18.44-fc-v130"),
+                   Some((6, 18, 44)));
+        assert_eq!(parse_kernel_version("5.4\n"), Some((5, 4, 0)));
+        assert_eq!(parse_kernel_version("2.6.32.27-0.2-xen"),
+                   Some((2, 6, 32)));
+        assert_eq!(parse_kernel_version("garbage"), None);
+    }
+}
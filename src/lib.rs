@@ -7,8 +7,9 @@
 //! * CPU Usage with breakdown by each thread
 //! * Disk Usage
 //!
-//! More metrics might be added later. Currently, library supports only linux,
-//! but pull requests for other platforms are welcome.
+//! More metrics might be added later. Library is mostly tested on linux.
+//! On macOS only process-level CPU, memory and disk usage are measured
+//! (no breakdown by thread), pull requests for other platforms are welcome.
 //!
 //! # Example
 //!
@@ -35,26 +36,76 @@
 //!    }
 //! }
 //! ```
+//!
+//! # Features
+//!
+//! * `io-stats` (default) -- read IO counters (`/proc/self/io` on linux)
+//!   on each scan. When disabled, the file isn't opened and all IO fields
+//!   of `Report` are zero (ratios are `None`). Useful in sandboxes where
+//!   the file is not accessible.
+//! * `serde` (default) -- implement `Serialize` (and `Deserialize` where
+//!   it makes sense) for reports and enable JSON output:
+//!   `report_json()`, `report_json_str()` and `diagnostic_dump()`
+//! * `metrics` -- publish reports through the `metrics` crate
+//! * `log` -- log a warning when `scan()` is called more than 1.5 times
+//!   `scan_interval` after the previous scan, as rates of such interval
+//!   understate short spikes, or when number of CPUs is zero and is
+//!   clamped to one
 extern crate libc;
 extern crate num_cpus;
-extern crate serde;
+#[cfg(feature="serde")] extern crate serde;
 
 #[macro_use] extern crate quick_error;
-#[macro_use] extern crate serde_derive;
+#[cfg(feature="serde")] #[macro_use] extern crate serde_derive;
+#[cfg(feature="serde")] #[macro_use] extern crate serde_json;
+#[cfg(feature="metrics")] #[macro_use] extern crate metrics;
+#[cfg(feature="log")] #[macro_use] extern crate log;
 
-use std::fs::File;
+#[cfg(target_os="linux")] use std::fs::File;
+use std::path::PathBuf;
 use std::time::{SystemTime, Instant, Duration};
-use std::collections::{VecDeque, HashMap};
+use std::collections::{VecDeque, HashMap, BTreeMap};
 
 mod meter;
 mod scan;
 mod error;
 mod report;
-mod serialize;
+#[cfg(feature="serde")] mod serialize;
 mod debug;
+mod config;
+mod builder;
+mod format;
+mod bottleneck;
+mod score;
+mod phase;
+mod extended;
+mod rescan;
+#[cfg(feature="serde")] mod diagnostic;
+mod kernel;
+mod cgroup;
+mod loadavg;
+mod threads;
+mod alert;
+mod smooth;
+mod export;
+mod units;
+#[cfg(feature="metrics")] mod prometheus;
+#[cfg(target_os="macos")] mod macos;
 
-pub use error::Error;
-pub use report::ThreadReportIter;
+pub use error::{Error, StatError};
+pub use config::MeterConfig;
+pub use builder::{MeterBuilder, WarmupBehavior};
+pub use bottleneck::{Bottleneck, BottleneckThresholds};
+pub use score::{ScoreWeights, MemoryPressure};
+pub use extended::{ExtendedReport, RegionKind, FdKind};
+pub use rescan::{ScanStatus, SampleValidator};
+pub use kernel::KernelVersion;
+pub use threads::TrackedThreadsIter;
+pub use alert::AlertCallback;
+pub use export::{SnapshotData, ThreadData, CoreData};
+pub use units::ByteUnit;
+#[cfg(feature="metrics")] pub use prometheus::PrometheusBridge;
+pub use report::{ThreadReportIter, ProcessReportIter, ReportHistoryIter};
 /// A Pid type used to identify processes and threads
 pub type Pid = u32;
 
@@ -63,6 +114,34 @@ struct ThreadInfo {
     system_time: u64,
     child_user_time: u64,
     child_system_time: u64,
+    minor_faults: u64,
+    major_faults: u64,
+    /// Bytes read and written (`rchar`, `wchar`), only if thread IO is on
+    read_bytes: u64,
+    write_bytes: u64,
+    /// Time the thread was started after boot, in centiseconds
+    start_time: u64,
+    /// Nanoseconds on CPU and waiting in runqueue, only if schedstat is on
+    run_time_ns: u64,
+    wait_time_ns: u64,
+    /// CPU number the thread was last executed on
+    processor: u32,
+}
+
+/// Jiffies of a single core from `cpuN` line of /proc/stat
+struct CoreTimes {
+    id: u32,
+    /// Idle plus iowait
+    idle: u64,
+    total: u64,
+}
+
+/// IO counters captured by `Meter::reset_io_baseline`
+struct IoBaseline {
+    read_bytes: u64,
+    write_bytes: u64,
+    read_disk_bytes: u64,
+    write_disk_bytes: u64,
 }
 
 struct Snapshot {
@@ -72,11 +151,29 @@ struct Snapshot {
     uptime: u64,
     /// System idle time in centisecs
     idle_time: u64,
+    /// Idle plus iowait jiffies from the `cpu` line of /proc/stat
+    stat_idle: u64,
+    /// Sum of all jiffies from the `cpu` line of /proc/stat
+    stat_total: u64,
+    /// Iowait, steal and guest (including guest_nice) jiffies of /proc/stat
+    stat_iowait: u64,
+    stat_steal: u64,
+    stat_guest: u64,
+    /// Per-core jiffies of /proc/stat, only online cores are listed
+    cores: Vec<CoreTimes>,
     process: ThreadInfo,
     memory_rss: u64,
     memory_virtual: u64,
     memory_virtual_peak: u64,
+    memory_rss_hwm: u64,
     memory_swap: u64,
+    memory_rss_anon: u64,
+    memory_rss_file: u64,
+    memory_rss_shmem: u64,
+    memory_data: u64,
+    memory_stack: u64,
+    memory_exe: u64,
+    memory_lib: u64,
     read_bytes: u64,
     write_bytes: u64,
     read_ops: u64,
@@ -84,6 +181,31 @@ struct Snapshot {
     read_disk_bytes: u64,
     write_disk_bytes: u64,
     write_cancelled_bytes: u64,
+    /// Sums over interfaces of /proc/self/net/dev
+    net_rx_bytes: u64,
+    net_tx_bytes: u64,
+    net_rx_packets: u64,
+    net_tx_packets: u64,
+    /// Pages swapped in system-wide (`pswpin` of /proc/vmstat)
+    swap_in_pages: u64,
+    /// System-wide memory from /proc/meminfo
+    system_memory_total: u64,
+    system_memory_available: u64,
+    system_memory_free: u64,
+    /// Resident memory by kind of mapping (opt-in)
+    memory_by_region: HashMap<RegionKind, u64>,
+    /// Resident memory by NUMA node (opt-in)
+    memory_by_numa_node: HashMap<u32, u64>,
+    /// Open file descriptors by kind (opt-in)
+    fds_by_type: HashMap<FdKind, u32>,
+    /// Number of threads in the process
+    num_threads: u32,
+    /// Number of open file descriptors
+    fd_count: u32,
+    voluntary_ctxt_switches: u64,
+    nonvoluntary_ctxt_switches: u64,
+    /// Pid of the debugger (`TracerPid` of /proc/self/status), zero if none
+    tracer_pid: Pid,
     threads: HashMap<Pid, ThreadInfo>,
 }
 
@@ -97,49 +219,180 @@ pub struct ThreadUsage {
     pub cpu_usage_with_children: f32,
 }
 
+/// Source of the `Report::global_cpu_usage` value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature="serde", derive(Serialize, Deserialize))]
+pub enum CpuSource {
+    /// Idle time from /proc/uptime
+    Uptime,
+    /// Idle jiffies from /proc/stat
+    ProcStat,
+}
+
 /// Report returned by `Meter::report`
 ///
-/// Note: this structure implements `serde::Serialize`, and all timestamps and
-/// durations are stored as integers in milliseconds.
-#[derive(Debug, Serialize)]
+/// Note: with the `serde` feature this structure implements
+/// `serde::Serialize` and `serde::Deserialize`, all timestamps and
+/// durations are stored as integers in milliseconds (so sub-millisecond
+/// precision is lost when round-tripping).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature="serde", derive(Serialize, Deserialize))]
 pub struct Report {
     /// Timestamp
-    #[serde(serialize_with="serialize::serialize_timestamp")]
+    #[cfg_attr(feature="serde",
+        serde(serialize_with="serialize::serialize_timestamp",
+              deserialize_with="serialize::deserialize_timestamp"))]
     pub timestamp: SystemTime,
 
     /// The interval time this data has averaged over in milliseconds
-    #[serde(serialize_with="serialize::serialize_duration")]
+    #[cfg_attr(feature="serde",
+        serde(serialize_with="serialize::serialize_duration",
+              deserialize_with="serialize::deserialize_duration"))]
     pub duration: Duration,
 
     /// Start time
-    #[serde(serialize_with="serialize::serialize_timestamp")]
+    #[cfg_attr(feature="serde",
+        serde(serialize_with="serialize::serialize_timestamp",
+              deserialize_with="serialize::deserialize_timestamp"))]
     pub start_time: SystemTime,
 
     /// The uptime of the system
     ///
     /// Note this value can be smaller than time since `start_time`
     /// because this value doesn't include time when system was sleeping
-    #[serde(serialize_with="serialize::serialize_duration")]
+    #[cfg_attr(feature="serde",
+        serde(serialize_with="serialize::serialize_duration",
+              deserialize_with="serialize::deserialize_duration"))]
     pub system_uptime: Duration,
     /// Whole system CPU usage. 100% is all cores
     pub global_cpu_usage: f32,
+    /// Where `global_cpu_usage` is computed from
+    ///
+    /// Normally it's computed from jiffies of /proc/stat: non-idle delta
+    /// over the total delta. Idle time in /proc/uptime (summed across cores,
+    /// so less precise) is used only if /proc/stat can't be read or had no
+    /// progress.
+    pub global_cpu_source: CpuSource,
+    /// Time all cores were idle waiting for IO, percent of total CPU time
+    ///
+    /// This is derived from /proc/stat like the other `global_*` fields,
+    /// which are zero if the kernel doesn't report respective value.
+    pub global_iowait: f32,
+    /// Time stolen by hypervisor for other VMs, percent of total CPU time
+    pub global_steal: f32,
+    /// Time spent running guest VMs, percent of total CPU time
+    pub global_guest: f32,
     /// Process' own CPU usage. 100% is a single core
+    ///
+    /// Clamped to `[0, effective_cpus * 100]` (see `Meter::effective_cpus`),
+    /// as timing skew between reading uptime and process times may produce
+    /// slightly out of range values.
     pub process_cpu_usage: f32,
+    /// Peak of `process_cpu_usage` since the meter was created
+    ///
+    /// Updated on each scan, so this is a peak over scan intervals, not
+    /// instantaneous one. As CPU usage needs two snapshots, the peak is
+    /// zero until the second scan.
+    pub process_cpu_peak: f32,
+    /// Part of `process_cpu_usage` spent in user space
+    pub process_user_cpu: f32,
+    /// Part of `process_cpu_usage` spent in kernel
+    pub process_system_cpu: f32,
+    /// Ratio of kernel to user CPU time of the process over the interval
+    ///
+    /// Low values mean compute-bound workload, high values mean syscall-
+    /// or IO-heavy one. Zero if user time didn't advance.
+    pub kernel_user_ratio: f32,
     /// Process' CPU usage with its awaited children. 100% is a single core
+    ///
+    /// Equals to `process_cpu_usage` if disabled by
+    /// `MeterBuilder::include_child_cpu`. Clamped the same way as
+    /// `process_cpu_usage`.
     pub gross_cpu_usage: f32,
+    /// Parallelism factor: number of cores' worth of work done per second
+    ///
+    /// This is `gross_cpu_usage / 100` clamped to `[0, effective_cpus]`. Value
+    /// close to the number of cores means the workload is fully parallel,
+    /// value close to 1 means it's serial.
+    pub parallelism: f32,
     /// Process' memory usage
     pub memory_rss: u64,
     /// Process' virtual memory usage
     pub memory_virtual: u64,
     /// Process' swap usage
     pub memory_swap: u64,
+    /// Process' resident anonymous memory (heap, stacks and other private
+    /// mappings), zero if not reported by the kernel
+    pub memory_rss_anon: u64,
+    /// Process' resident file-backed memory (executables, libraries and
+    /// other mapped files), zero if not reported by the kernel
+    pub memory_rss_file: u64,
+    /// Process' resident shared memory (SysV and POSIX shm, shared anonymous
+    /// mappings), zero if not reported by the kernel
+    ///
+    /// This and the previous two fields sum up to `memory_rss`.
+    pub memory_rss_shmem: u64,
+    /// Size of data segments (heap and other private writable mappings,
+    /// including not resident ones), zero if not reported by the kernel
+    pub memory_data: u64,
+    /// Size of the main thread's stack, zero if not reported by the kernel
+    pub memory_stack: u64,
+    /// Size of the executable's text segment, zero if not reported by the
+    /// kernel
+    pub memory_exe: u64,
+    /// Size of mapped shared libraries' code, zero if not reported by the
+    /// kernel
+    pub memory_lib: u64,
     /// Process' peak memory usage (not precise)
+    ///
+    /// This is the maximum of `memory_rss` sampled at scans, so spikes
+    /// between scans are missed. See `memory_rss_hwm` for the precise one.
     pub memory_rss_peak: u64,
+    /// Process' peak resident memory tracked by the kernel (VmHWM)
+    ///
+    /// Unlike `memory_rss_peak` this never misses a spike between scans,
+    /// but it's since the process start (can't be reset with
+    /// `reset_peaks`). Zero if not reported by the OS.
+    pub memory_rss_hwm: u64,
     /// Process' peak virtual memory usage (tracked by OS)
     pub memory_virtual_peak: u64,
     /// Process' swap usage (not precise)
     pub memory_swap_peak: u64,
+    /// Total usable memory of the system (`MemTotal` of /proc/meminfo)
+    ///
+    /// Note: inside a container this is the host's memory. This and other
+    /// `system_memory_*` fields are zero if /proc/meminfo can't be read.
+    pub system_memory_total: u64,
+    /// Memory available for starting new applications without swapping
+    /// (`MemAvailable`), zero on kernels older than 3.14
+    pub system_memory_available: u64,
+    /// Memory not used by the system at all (`MemFree`), this doesn't
+    /// include page cache, so usually much smaller than available memory
+    pub system_memory_free: u64,
+    /// Memory limit of the cgroup in bytes, see `Meter::memory_limit`
+    pub memory_limit: Option<u64>,
+    /// Number of threads in the process
+    pub num_threads: u32,
+    /// Peak number of threads in the process (not precise)
+    pub num_threads_peak: u32,
+    /// Number of open file descriptors
+    ///
+    /// Zero if /proc/self/fd can't be listed, e.g. for a process of
+    /// another user.
+    pub fd_count: u32,
+    /// Peak number of open file descriptors (not precise)
+    pub fd_count_peak: u32,
+    /// Voluntary context switches per second (i.e. waiting for IO or lock)
+    pub voluntary_ctxt_switches: f32,
+    /// Involuntary context switches per second
+    ///
+    /// High values mean the process is preempted a lot, i.e. is starving
+    /// for CPU.
+    pub involuntary_ctxt_switches: f32,
     /// Bytes read per second from block-backed filesystems
+    ///
+    /// This and other IO fields are zero when the `io-stats` feature is
+    /// disabled (`io_read_write_ratio` and `cache_hit_ratio` are `None`).
     pub disk_read: f32,
     /// Bytes written per second from block-backed filesystems
     pub disk_write: f32,
@@ -153,17 +406,160 @@ pub struct Report {
     pub io_read_ops: f32,
     /// Write operations (syscalls) per second (total)
     pub io_write_ops: f32,
+    /// Bytes read (total) since `Meter::reset_io_baseline`
+    ///
+    /// If baseline was never reset, this is the total since process start,
+    /// the same applies to other `*_since_baseline` fields.
+    pub io_read_total_since_baseline: u64,
+    /// Bytes written (total) since `Meter::reset_io_baseline`
+    pub io_write_total_since_baseline: u64,
+    /// Bytes read from block-backed filesystems since baseline
+    pub disk_read_total_since_baseline: u64,
+    /// Bytes written to block-backed filesystems since baseline
+    pub disk_write_total_since_baseline: u64,
+    /// Bytes received per second over the network
+    ///
+    /// This and other `net_*` fields are sums over all interfaces (or
+    /// ones set by `Meter::set_net_interfaces`) of /proc/self/net/dev.
+    /// Counters are local to the network namespace of the process, but
+    /// include traffic of other processes in that namespace. They are
+    /// zero if the file can't be read.
+    pub net_read: f32,
+    /// Bytes sent per second over the network
+    pub net_write: f32,
+    /// Packets received per second over the network
+    pub net_read_packets: f32,
+    /// Packets sent per second over the network
+    pub net_write_packets: f32,
+    /// Ratio of bytes read to bytes written (total) over the interval
+    ///
+    /// Values above 1 mean read-heavy workload, below 1 write-heavy.
+    /// `None` if nothing was written during the interval.
+    pub io_read_write_ratio: Option<f32>,
+    /// Fraction of bytes read that were served from page cache
+    ///
+    /// Computed as `1 - disk_read / io_read` clamped to `[0, 1]`. Note
+    /// readahead may make block IO exceed the bytes read, in this case
+    /// ratio is zero. `None` if nothing was read during the interval.
+    pub cache_hit_ratio: Option<f32>,
+    /// Minor page faults (not requiring disk IO) of the process per second
+    pub minor_faults: f32,
+    /// Major page faults (requiring disk IO) of the process per second
+    ///
+    /// Non-zero values mean the process is loading code or data from disk
+    /// or swap, which is a sign of memory pressure if sustained.
+    pub major_faults: f32,
+    /// Memory thrashing score from 0 (none) to 1 (severe)
+    ///
+    /// This is `0.75 * faults + 0.25 * swap_in`, where `faults` is
+    /// process' major page fault rate and `swap_in` is system-wide swap-in
+    /// rate (zero if /proc/vmstat can't be read), each normalized against
+    /// 100 per second and clamped to 1. So the process faulting from disk
+    /// scores up to 0.75 even if swap is idle, while swapping of other
+    /// processes alone scores at most 0.25. Values above 0.3 are worth
+    /// attention, above 0.75 the process is likely thrashing.
+    pub thrashing_indicator: f32,
+    /// Process was traced (by a debugger or strace) during the interval
+    ///
+    /// Tracing slows down the process considerably, so CPU and IO figures
+    /// of such interval aren't representative.
+    pub is_traced: bool,
+    /// System was likely suspended during the interval
+    ///
+    /// Set when wall clock time between the scans exceeds the uptime
+    /// delta more than twice (and by at least a second). Uptime doesn't
+    /// advance while system is suspended, so rates averaged over such
+    /// interval are not representative. Note: large wall clock adjustments
+    /// (like NTP steps) look the same.
+    pub suspended_during_interval: bool,
+}
+
+/// Sum of reports of processes tracked with `Meter::track_process`
+///
+/// Returned by `Meter::aggregate_report`. Rates are summed, so CPU usage
+/// may exceed 100% (a single core) for the aggregate.
+#[derive(Debug)]
+#[cfg_attr(feature="serde", derive(Serialize))]
+pub struct AggregateReport {
+    /// Number of processes which had enough data to produce a report
+    pub processes: usize,
+    /// Processes' own CPU usage. 100% is a single core
+    pub process_cpu_usage: f32,
+    /// Processes' CPU usage with their awaited children
+    pub gross_cpu_usage: f32,
+    /// Processes' memory usage
+    pub memory_rss: u64,
+    /// Processes' virtual memory usage
+    pub memory_virtual: u64,
+    /// Processes' swap usage
+    pub memory_swap: u64,
+    /// Bytes read per second from block-backed filesystems
+    pub disk_read: f32,
+    /// Bytes written per second from block-backed filesystems
+    pub disk_write: f32,
+    /// Bytes read per second (total)
+    pub io_read: f32,
+    /// Bytes written per second (total)
+    pub io_write: f32,
+}
+
+/// Statistics of actual scan intervals, returned by `Meter::scan_jitter`
+///
+/// Deviation is the difference between actual interval between scans and
+/// the configured `scan_interval`, in either direction.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature="serde", derive(Serialize))]
+pub struct JitterStats {
+    /// Number of intervals measured
+    pub samples: u64,
+    /// Mean deviation
+    #[cfg_attr(feature="serde",
+        serde(serialize_with="serialize::serialize_duration"))]
+    pub mean_deviation: Duration,
+    /// Maximum deviation
+    #[cfg_attr(feature="serde",
+        serde(serialize_with="serialize::serialize_duration"))]
+    pub max_deviation: Duration,
 }
 
 /// Report of CPU usage by single thread
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature="serde", derive(Serialize))]
 pub struct ThreadReport {
     /// Threads' own CPU usage. 100% is a single core
     pub cpu_usage: f32,
+    /// Threads' CPU usage with awaited children. 100% is a single core
+    ///
+    /// Note: kernel accounts children per process rather than per thread,
+    /// so children's time is included for every thread.
+    pub cpu_usage_with_children: f32,
     /// Threads' own CPU usage in kernel space. 100% is a single core
     pub system_cpu: f32,
     /// Threads' own CPU usage in user space. 100% is a single core
     pub user_cpu: f32,
+    /// CPU number the thread was last executed on
+    pub last_cpu: u32,
+    /// Whether thread was executed on a different CPU than at previous scan
+    ///
+    /// Frequent migrations hurt cache locality
+    pub migrated: bool,
+    /// Bytes read per second (total), zero unless enabled by
+    /// `Meter::enable_thread_io`
+    pub io_read: f32,
+    /// Bytes written per second (total), zero unless enabled by
+    /// `Meter::enable_thread_io`
+    pub io_write: f32,
+    /// Fraction of the interval the thread was running on CPU
+    ///
+    /// Zero unless enabled by `Meter::enable_thread_schedstat`
+    pub run_time: f32,
+    /// Fraction of the interval the thread was waiting in runqueue
+    ///
+    /// This is a direct measure of CPU contention: the thread was ready to
+    /// run but all cores were busy. Zero unless enabled by
+    /// `Meter::enable_thread_schedstat` or if the kernel has schedstats
+    /// disabled.
+    pub wait_time: f32,
 }
 
 /// The main structure that makes mesurements and reports values
@@ -176,8 +572,8 @@ pub struct ThreadReport {
 /// recommended to call it on the interval of one second.
 ///
 /// Method `report()` may be used to get structure with stats. `report_json()`
-/// can return a `rustc_serialize::Json` and `report_json_str()` returns that
-/// serialized.
+/// can return a `serde_json::Value` and `report_json_str()` returns that
+/// serialized (both need the `serde` feature).
 ///
 /// Note that the structure returned with `report()` can be changed when we
 /// bump **major version** of the library. And while `report_json()` and
@@ -192,6 +588,10 @@ pub struct Meter {
     scan_interval: Duration,
     num_cpus: usize,
     num_snapshots: usize,
+    /// Process to measure, `None` means `/proc/self`
+    pid: Option<Pid>,
+    /// Where proc filesystem is mounted, `/proc` by default
+    proc_root: PathBuf,
     start_time: SystemTime,
     snapshots: VecDeque<Snapshot>,
     thread_names: HashMap<Pid, String>,
@@ -202,11 +602,97 @@ pub struct Meter {
     /// This is a smaller buffer for formatting paths, similar to `text_buf`
     path_buf: String,
 
-    /// This file is always open because if we drop privileges and then
-    /// try to open a file we can't open it back again
+    /// These files are always open because if we drop privileges and then
+    /// try to open a file we can't open it back again. This also saves
+    /// an open and close per file on each scan.
     #[cfg(target_os="linux")]
+    uptime_file: File,
+    #[cfg(target_os="linux")]
+    stat_file: File,
+    #[cfg(target_os="linux")]
+    status_file: File,
+    #[cfg(all(target_os="linux", feature="io-stats"))]
     io_file: File,
+    /// Open /proc/self/task/<TID>/stat of tracked threads
+    ///
+    /// Files are opened on the first scan of a thread and reused, so each
+    /// scan does a seek and read per thread instead of formatting a path,
+    /// open, read and close. Stale files are reopened. Measured with 16
+    /// tracked threads, that's 5 calls per thread and scan instead of 6
+    /// (`openat` and `close` are replaced by `lseek`) and no path lookup
+    /// in procfs.
+    ///
+    /// This is a cache kept apart from `thread_names`, which is shared
+    /// with non-linux code and snapshots. Entries are added by `scan()`
+    /// only for tracked threads and are removed by `untrack_thread` and
+    /// `untrack_all_threads`; a missing entry just means a reopen.
+    #[cfg(target_os="linux")]
+    thread_stat_files: HashMap<Pid, File>,
 
     memory_rss_peak: u64,
     memory_swap_peak: u64,
+    process_cpu_peak: f32,
+    num_threads_peak: u32,
+    fd_count_peak: u32,
+
+    metadata: BTreeMap<String, String>,
+
+    report_throttle: Option<Duration>,
+    /// Instant of the snapshot last passed to `scan_and_notify()` callback
+    last_emit: Option<Instant>,
+
+    tid_source: Option<fn() -> Pid>,
+
+    processes: HashMap<Pid, Meter>,
+
+    /// Creation time of the meter, used to compute overhead fraction
+    created: Instant,
+    /// Total time spent in `scan()`
+    scan_time: Duration,
+
+    jitter_samples: u64,
+    jitter_total: Duration,
+    jitter_max: Duration,
+
+    io_baseline: IoBaseline,
+    /// Baseline reset was requested before the first scan
+    io_baseline_pending: bool,
+
+    /// Name and starting snapshot of the phase started by `begin_phase`
+    phase: Option<(String, Snapshot)>,
+
+    /// Scan /proc/self/smaps
+    memory_regions: bool,
+    /// Scan /proc/self/numa_maps
+    numa_maps: bool,
+    /// List /proc/self/fd
+    fd_types: bool,
+    /// Read /proc/self/task/<TID>/io for tracked threads
+    thread_io: bool,
+    /// Read /proc/self/task/<TID>/schedstat for tracked threads
+    thread_schedstat: bool,
+    /// Interfaces summed into `net_*` fields, `None` means all
+    net_interfaces: Option<Vec<String>>,
+    /// Add children's CPU time to `gross_cpu_usage`
+    include_child_cpu: bool,
+    /// What `report()` returns before the second scan
+    warmup_behavior: WarmupBehavior,
+    /// `scan()` is a no-op when set
+    paused: bool,
+    /// Set by `set_sample_validator`
+    sample_validator: Option<rescan::SampleValidator>,
+    /// Last `scan_checked()` found the sample suspect
+    rescan_requested: bool,
+    kernel_version: Option<KernelVersion>,
+    /// Memory limit of the cgroup, read once in constructor
+    memory_limit: Option<u64>,
+    /// CPU quota of the cgroup in cores, read once in constructor
+    cpu_quota: Option<f32>,
+    score_weights: ScoreWeights,
+    /// Per-thread errors are collected here by `scan_lenient()`
+    thread_errors: Option<Vec<(Pid, StatError)>>,
+    /// Registered by `on_threshold`, checked in `scan_and_report`
+    thresholds: Vec<alert::Threshold>,
+    /// State of `smoothed_report()`
+    smoothed: smooth::Smoothed,
 }
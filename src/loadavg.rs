@@ -0,0 +1,62 @@
+use {Meter, Error};
+use error::LoadAvgError;
+#[cfg(target_os="macos")] use macos;
+
+
+impl Meter {
+    /// Returns 1, 5 and 15 minute load averages of the system
+    ///
+    /// This reads /proc/loadavg on each call, values aren't kept in the
+    /// snapshots and are not affected by `scan()`.
+    #[cfg(target_os="linux")]
+    pub fn load_average(&self) -> Result<(f32, f32, f32), Error> {
+        use std::fs::File;
+        use std::io::Read;
+
+        let mut buf = String::with_capacity(64);
+        File::open(self.proc_root.join("loadavg"))
+            .and_then(|mut f| f.read_to_string(&mut buf))
+            .map_err(LoadAvgError::Io)?;
+        Ok(parse_loadavg(&buf)?)
+    }
+    /// Returns 1, 5 and 15 minute load averages of the system
+    ///
+    /// Values are fetched with `getloadavg` on each call.
+    #[cfg(target_os="macos")]
+    pub fn load_average(&self) -> Result<(f32, f32, f32), Error> {
+        let avg = macos::load_average().map_err(LoadAvgError::Io)?;
+        Ok((avg[0] as f32, avg[1] as f32, avg[2] as f32))
+    }
+    /// Returns 1, 5 and 15 minute load averages of the system
+    ///
+    /// Not supported on this platform, always returns an error.
+    #[cfg(not(any(target_os="linux", target_os="macos")))]
+    pub fn load_average(&self) -> Result<(f32, f32, f32), Error> {
+        use std::io;
+
+        Err(LoadAvgError::Io(io::ErrorKind::Unsupported.into()).into())
+    }
+}
+
+/// Parses /proc/loadavg, which looks like `0.42 0.31 0.25 1/123 4567`
+#[cfg(target_os="linux")]
+fn parse_loadavg(text: &str) -> Result<(f32, f32, f32), LoadAvgError> {
+    let mut iter = text.split_whitespace();
+    let one = iter.next().ok_or(LoadAvgError::BadFormat)?.parse()?;
+    let five = iter.next().ok_or(LoadAvgError::BadFormat)?.parse()?;
+    let fifteen = iter.next().ok_or(LoadAvgError::BadFormat)?.parse()?;
+    Ok((one, five, fifteen))
+}
+
+#[cfg(all(test, target_os="linux"))]
+mod test {
+    use super::parse_loadavg;
+
+    #[test]
+    fn loadavg() {
+        assert_eq!(parse_loadavg("0.42 0.31 0.25 1/123 4567\n").unwrap(),
+                   (0.42, 0.31, 0.25));
+        assert!(parse_loadavg("0.42 0.31\n").is_err());
+        assert!(parse_loadavg("a b c\n").is_err());
+    }
+}
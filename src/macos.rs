@@ -0,0 +1,95 @@
+//! Process and system counters on macOS via libproc and mach APIs
+// mach functions in libc are deprecated in favor of the `mach2` crate, but
+// they are still there and we don't want another dependency
+#![allow(deprecated)]
+
+use std::io;
+use std::mem;
+
+use libc::{c_int, c_void, c_double, getpid, getloadavg};
+use libc::{proc_pidinfo, proc_taskinfo, PROC_PIDTASKINFO};
+use libc::{proc_pid_rusage, rusage_info_v2, RUSAGE_INFO_V2};
+use libc::{host_statistics, mach_host_self, host_cpu_load_info};
+use libc::{HOST_CPU_LOAD_INFO, HOST_CPU_LOAD_INFO_COUNT, KERN_SUCCESS};
+use libc::{mach_absolute_time, mach_timebase_info};
+
+use Pid;
+
+
+/// Nanoseconds in a centisecond (the unit used for uptime and CPU times)
+const NANOS_PER_CENTISEC: u64 = 10_000_000;
+
+fn pid_or_self(pid: Option<Pid>) -> c_int {
+    pid.map(|pid| pid as c_int).unwrap_or_else(|| unsafe { getpid() })
+}
+
+/// Returns task info (memory, faults, threads) of the process
+pub fn task_info(pid: Option<Pid>) -> io::Result<proc_taskinfo> {
+    unsafe {
+        let mut info: proc_taskinfo = mem::zeroed();
+        let size = mem::size_of::<proc_taskinfo>() as c_int;
+        let res = proc_pidinfo(pid_or_self(pid), PROC_PIDTASKINFO, 0,
+            &mut info as *mut _ as *mut c_void, size);
+        if res != size {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(info)
+    }
+}
+
+/// Returns resource usage (CPU times, disk IO) of the process
+///
+/// Note: times are in mach absolute time units, see `abs_to_centisecs`
+pub fn rusage(pid: Option<Pid>) -> io::Result<rusage_info_v2> {
+    unsafe {
+        let mut info: rusage_info_v2 = mem::zeroed();
+        let res = proc_pid_rusage(pid_or_self(pid), RUSAGE_INFO_V2,
+            &mut info as *mut _ as *mut _);
+        if res != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(info)
+    }
+}
+
+/// Returns system-wide CPU ticks (user, system, idle, nice) of all cores
+pub fn host_cpu_ticks() -> io::Result<host_cpu_load_info> {
+    unsafe {
+        let mut info: host_cpu_load_info = mem::zeroed();
+        let mut count = HOST_CPU_LOAD_INFO_COUNT;
+        let res = host_statistics(mach_host_self(), HOST_CPU_LOAD_INFO,
+            &mut info as *mut _ as *mut _, &mut count);
+        if res != KERN_SUCCESS {
+            return Err(io::Error::other("host_statistics failed"));
+        }
+        Ok(info)
+    }
+}
+
+/// Converts mach absolute time units into centiseconds
+pub fn abs_to_centisecs(value: u64) -> u64 {
+    let mut timebase = mach_timebase_info { numer: 0, denom: 0 };
+    unsafe { mach_timebase_info(&mut timebase) };
+    if timebase.denom == 0 {
+        return value / NANOS_PER_CENTISEC;
+    }
+    // 128-bit intermediate because numer may be large on arm64
+    let nanos = value as u128 * timebase.numer as u128
+        / timebase.denom as u128;
+    (nanos / NANOS_PER_CENTISEC as u128) as u64
+}
+
+/// Returns time since boot (not including sleep) in centiseconds
+pub fn uptime() -> u64 {
+    abs_to_centisecs(unsafe { mach_absolute_time() })
+}
+
+/// Returns 1, 5 and 15 minute load averages of the system
+pub fn load_average() -> io::Result<[c_double; 3]> {
+    let mut avg = [0.; 3];
+    let res = unsafe { getloadavg(avg.as_mut_ptr(), 3) };
+    if res != 3 {
+        return Err(io::Error::other("getloadavg failed"));
+    }
+    Ok(avg)
+}
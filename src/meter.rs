@@ -1,12 +1,34 @@
-use std::fs::File;
-use std::time::{Duration, SystemTime};
-use std::collections::{VecDeque, HashMap};
+use std::cmp::max;
+#[cfg(target_os="linux")] use std::fs::File;
+use std::time::{Duration, SystemTime, Instant};
+use std::path::Path;
+use std::collections::{VecDeque, HashMap, BTreeMap};
 
 use num_cpus;
 
-use {Meter, Error, Pid};
-use error::IoStatError;
+use {Meter, MeterBuilder, Error, Pid, IoBaseline, Snapshot, JitterStats};
+use builder::WarmupBehavior;
+use score::ScoreWeights;
+#[cfg(all(target_os="linux", feature="io-stats"))] use error::IoStatError;
+#[cfg(target_os="linux")] use scan::{proc_path, proc_file};
+use kernel::read_kernel_version;
+use cgroup::{CGROUP_ROOT, read_limits};
 
+/// Initial capacity of `Meter::text_buf`, fits /proc/vmstat and status
+const TEXT_BUF_CAPACITY: usize = 8192;
+/// Initial capacity of `Meter::path_buf`
+const PATH_BUF_CAPACITY: usize = 100;
+
+/// Clamps number of CPUs to at least one, as we divide by it
+///
+/// `num_cpus::get()` never returns zero, but it may be set explicitly.
+fn clamp_num_cpus(num_cpus: usize) -> usize {
+    #[cfg(feature="log")]
+    if num_cpus == 0 {
+        warn!("self-meter: number of CPUs is zero, using 1 instead");
+    }
+    max(num_cpus, 1)
+}
 
 impl Meter {
     /// Create a new meter with scan_interval
@@ -19,42 +41,194 @@ impl Meter {
     ///
     /// When creating a `Meter` object we are trying to discover the number
     /// of processes on the system. If that fails, we return error.
+    ///
+    /// See `MeterBuilder` for more options.
     pub fn new(scan_interval: Duration) -> Result<Meter, Error> {
-        Meter::_new(scan_interval)
+        MeterBuilder::new(scan_interval).build()
+    }
+    /// Create a new meter that measures another process
+    ///
+    /// Works like `new()` but reads `/proc/<pid>` instead of `/proc/self`.
+    /// Note: you need enough privileges to read `/proc/<pid>/io` of the
+    /// process, which usually means the same user.
+    pub fn new_for_pid(pid: Pid, scan_interval: Duration)
+        -> Result<Meter, Error>
+    {
+        Meter::new_for_pid_in_root(pid, "/proc", scan_interval)
+    }
+    /// Create a new meter that measures another process in a proc mount
+    ///
+    /// Works like `new_for_pid()` but reads `<proc_root>/<pid>`. All files,
+    /// including system-wide ones like `uptime` and `stat`, are read
+    /// from `proc_root`, and processes added by `track_process()` use it
+    /// too.
+    ///
+    /// Pids in a proc filesystem are local to the PID namespace it was
+    /// mounted from. So if a sidecar container has the `/proc` of another
+    /// container mounted at `/host-proc`, `pid` must be the one that
+    /// process has in its own namespace (e.g. `1` for the container's
+    /// init), not the one seen in the sidecar's `/proc`. Cgroup limits
+    /// (`memory_limit()`, `effective_cpus()`) are those of the target's
+    /// cgroup, looked up in `<proc_root>/<pid>/cgroup`, and are unknown if
+    /// the cgroup isn't visible under our /sys/fs/cgroup.
+    pub fn new_for_pid_in_root<P: AsRef<Path>>(pid: Pid, proc_root: P,
+        scan_interval: Duration)
+        -> Result<Meter, Error>
+    {
+        Meter::_new(scan_interval, Some(pid), proc_root.as_ref())
     }
     #[cfg(target_os="linux")]
-    fn _new(scan_interval: Duration) -> Result<Meter, Error> {
-        let io_file = File::open("/proc/self/io").map_err(IoStatError::Io)?;
+    pub(crate) fn _new(scan_interval: Duration, pid: Option<Pid>,
+        proc_root: &Path)
+        -> Result<Meter, Error>
+    {
+        let mut path_buf = String::with_capacity(PATH_BUF_CAPACITY);
+        proc_file(&mut path_buf, proc_root, "uptime");
+        let uptime_file = File::open(&path_buf)
+            .map_err(|e| Error::Uptime(e.into()))?;
+        proc_path(&mut path_buf, proc_root, pid, "stat");
+        let stat_file = File::open(&path_buf)
+            .map_err(|e| Error::Stat(e.into()))?;
+        proc_path(&mut path_buf, proc_root, pid, "status");
+        let status_file = File::open(&path_buf)
+            .map_err(|e| Error::Status(e.into()))?;
+        proc_path(&mut path_buf, proc_root, pid, "io");
+        #[cfg(feature="io-stats")]
+        let io_file = File::open(&path_buf).map_err(IoStatError::Io)?;
+        let (memory_limit, cpu_quota) =
+            read_limits(Path::new(CGROUP_ROOT), proc_root, pid);
         Ok(Meter {
-            scan_interval: scan_interval,
-            num_cpus: num_cpus::get(),
+            scan_interval,
+            num_cpus: clamp_num_cpus(num_cpus::get()),
             num_snapshots: 10,
+            pid,
+            proc_root: proc_root.to_path_buf(),
             start_time: SystemTime::now(),
             snapshots: VecDeque::with_capacity(10),
             thread_names: HashMap::new(),
-            text_buf: String::with_capacity(1024),
-            path_buf: String::with_capacity(100),
-            io_file: io_file,
+            text_buf: String::with_capacity(TEXT_BUF_CAPACITY),
+            path_buf,
+            uptime_file,
+            stat_file,
+            status_file,
+            #[cfg(feature="io-stats")]
+            io_file,
+            thread_stat_files: HashMap::new(),
 
             memory_swap_peak: 0,
+            process_cpu_peak: 0.,
             memory_rss_peak: 0,
+            num_threads_peak: 0,
+            fd_count_peak: 0,
+
+            metadata: BTreeMap::new(),
+
+            report_throttle: None,
+            last_emit: None,
+
+            tid_source: None,
+
+            processes: HashMap::new(),
+
+            created: Instant::now(),
+            scan_time: Duration::new(0, 0),
+
+            jitter_samples: 0,
+            jitter_total: Duration::new(0, 0),
+            jitter_max: Duration::new(0, 0),
+
+            io_baseline: IoBaseline::new(),
+            io_baseline_pending: false,
+
+            phase: None,
+
+            memory_regions: false,
+            numa_maps: false,
+            fd_types: false,
+            thread_io: false,
+            thread_schedstat: false,
+            net_interfaces: None,
+            include_child_cpu: true,
+            warmup_behavior: WarmupBehavior::None,
+            paused: false,
+
+            sample_validator: None,
+            rescan_requested: false,
+            kernel_version: read_kernel_version(proc_root),
+            memory_limit,
+            cpu_quota,
+            score_weights: ScoreWeights::default(),
+            thread_errors: None,
+            thresholds: Vec::new(),
+            smoothed: None,
         })
     }
 
     #[cfg(not(target_os="linux"))]
-    fn _new(scan_interval: Duration) -> Result<Meter, Error> {
+    pub(crate) fn _new(scan_interval: Duration, pid: Option<Pid>,
+        proc_root: &Path)
+        -> Result<Meter, Error>
+    {
+        let (memory_limit, cpu_quota) =
+            read_limits(Path::new(CGROUP_ROOT), proc_root, pid);
         Ok(Meter {
-            scan_interval: scan_interval,
-            num_cpus: num_cpus::get(),
+            scan_interval,
+            num_cpus: clamp_num_cpus(num_cpus::get()),
             num_snapshots: 10,
+            pid,
+            proc_root: proc_root.to_path_buf(),
             start_time: SystemTime::now(),
             snapshots: VecDeque::with_capacity(10),
             thread_names: HashMap::new(),
-            text_buf: String::with_capacity(1024),
-            path_buf: String::with_capacity(100),
+            text_buf: String::with_capacity(TEXT_BUF_CAPACITY),
+            path_buf: String::with_capacity(PATH_BUF_CAPACITY),
 
             memory_swap_peak: 0,
+            process_cpu_peak: 0.,
             memory_rss_peak: 0,
+            num_threads_peak: 0,
+            fd_count_peak: 0,
+
+            metadata: BTreeMap::new(),
+
+            report_throttle: None,
+            last_emit: None,
+
+            tid_source: None,
+
+            processes: HashMap::new(),
+
+            created: Instant::now(),
+            scan_time: Duration::new(0, 0),
+
+            jitter_samples: 0,
+            jitter_total: Duration::new(0, 0),
+            jitter_max: Duration::new(0, 0),
+
+            io_baseline: IoBaseline::new(),
+            io_baseline_pending: false,
+
+            phase: None,
+
+            memory_regions: false,
+            numa_maps: false,
+            fd_types: false,
+            thread_io: false,
+            thread_schedstat: false,
+            net_interfaces: None,
+            include_child_cpu: true,
+            warmup_behavior: WarmupBehavior::None,
+            paused: false,
+
+            sample_validator: None,
+            rescan_requested: false,
+            kernel_version: read_kernel_version(proc_root),
+            memory_limit,
+            cpu_quota,
+            score_weights: ScoreWeights::default(),
+            thread_errors: None,
+            thresholds: Vec::new(),
+            smoothed: None,
         })
     }
 
@@ -64,45 +238,420 @@ impl Meter {
     pub fn track_thread(&mut self, tid: Pid, name: &str) {
         self.thread_names.insert(tid, name.to_string());
     }
-    /// Stop tracking specified thread (for example if it's dead)
+    /// Stop tracking specified thread
+    ///
+    /// Threads which have exited are untracked automatically by `scan()`.
     pub fn untrack_thread(&mut self, tid: Pid) {
         self.thread_names.remove(&tid);
+        #[cfg(target_os="linux")]
+        self.thread_stat_files.remove(&tid);
         for s in &mut self.snapshots {
             s.threads.remove(&tid);
         }
     }
-    /// Add current thread using `track_thread`, returns thread id
-    #[cfg(target_os="linux")]
-    pub fn track_current_thread(&mut self, name: &str) -> Pid {
-        use libc::{syscall, SYS_gettid};
-        let tid = unsafe { syscall(SYS_gettid) } as Pid;
-        self.track_thread(tid, name);
-        return tid;
+    /// Stop tracking all threads
+    ///
+    /// Statistics of threads are removed from the history too.
+    pub fn untrack_all_threads(&mut self) {
+        self.thread_names.clear();
+        #[cfg(target_os="linux")]
+        self.thread_stat_files.clear();
+        for s in &mut self.snapshots {
+            s.threads.clear();
+        }
+    }
+    /// Change name of the tracked thread, keeping its statistics
+    ///
+    /// Unlike `untrack_thread` followed by `track_thread`, this keeps
+    /// thread's history, so the next report has proper CPU usage. Returns
+    /// `false` (and does nothing) if thread isn't tracked.
+    pub fn rename_thread(&mut self, tid: Pid, new_name: &str) -> bool {
+        match self.thread_names.get_mut(&tid) {
+            Some(name) => {
+                name.truncate(0);
+                name.push_str(new_name);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Enable reading IO counters of each tracked thread
+    ///
+    /// This fills `io_read` and `io_write` of the `ThreadReport`, but
+    /// doubles the number of files read on each scan for tracked threads.
+    pub fn enable_thread_io(&mut self, enabled: bool) {
+        self.thread_io = enabled;
+    }
+    /// Enable reading scheduler statistics of each tracked thread
+    ///
+    /// This fills `run_time` and `wait_time` of the `ThreadReport` from
+    /// /proc/self/task/<TID>/schedstat. If the kernel doesn't provide
+    /// the file (schedstats are disabled), values are zero.
+    pub fn enable_thread_schedstat(&mut self, enabled: bool) {
+        self.thread_schedstat = enabled;
+    }
+    /// Restrict network statistics to the listed interfaces
+    ///
+    /// By default (or with `None`) `net_*` fields of the report are sums
+    /// over all interfaces in /proc/self/net/dev, including loopback.
+    pub fn set_net_interfaces(&mut self, interfaces: Option<Vec<String>>) {
+        self.net_interfaces = interfaces;
     }
     /// Add current thread using `track_thread`, returns thread id
     ///
-    /// Non-linux is not supported yet (no-op)
-    #[cfg(not(target_os="linux"))]
-    pub fn track_current_thread(&mut self, _name: &str) -> Pid {
-        // TODO(tailhook) OS X and windows
-        0
+    /// Non-linux is not supported yet (no-op, returns zero) unless thread
+    /// id source is set with `set_tid_source`
+    pub fn track_current_thread(&mut self, name: &str) -> Pid {
+        match self.current_tid() {
+            Some(tid) => {
+                self.track_thread(tid, name);
+                tid
+            }
+            None => 0,
+        }
     }
     /// Remove current thread using `untrack_thread`
-    #[cfg(target_os="linux")]
+    ///
+    /// Non-linux is not supported yet (no-op) unless thread id source is
+    /// set with `set_tid_source`
     pub fn untrack_current_thread(&mut self) {
-        use libc::{syscall, SYS_gettid};
-        let tid = unsafe { syscall(SYS_gettid) } as Pid;
-        self.untrack_thread(tid);
+        if let Some(tid) = self.current_tid() {
+            self.untrack_thread(tid);
+        }
     }
-    /// Remove current thread using `untrack_thread`
+    /// Start tracking another process
     ///
-    /// Non-linux is not supported yet (no-op)
-    #[cfg(not(target_os="linux"))]
-    pub fn untrack_current_thread(&mut self) {
-        // TODO
+    /// Process is scanned on each `scan()` and its report is returned by
+    /// `process_reports()`. Scanning shares text buffers with this meter,
+    /// so tracking a number of processes is cheaper than having a `Meter`
+    /// per process.
+    pub fn track_process(&mut self, pid: Pid) -> Result<(), Error> {
+        let mut meter = Meter::_new(self.scan_interval, Some(pid),
+                                    &self.proc_root)?;
+        // buffers are borrowed from the parent meter while scanning
+        meter.text_buf = String::new();
+        meter.path_buf = String::new();
+        self.processes.insert(pid, meter);
+        Ok(())
+    }
+    /// Stop tracking specified process (for example if it's dead)
+    pub fn untrack_process(&mut self, pid: Pid) {
+        self.processes.remove(&pid);
+    }
+    /// Override the function returning id of the current thread
+    ///
+    /// This is used by `track_current_thread` and `untrack_current_thread`
+    /// and is mostly useful for tests. By default `gettid` syscall is used.
+    pub fn set_tid_source(&mut self, source: fn() -> Pid) {
+        self.tid_source = Some(source);
+    }
+    /// Override number of CPUs used to normalize global CPU usage
+    ///
+    /// Zero is clamped to one. Explicit value overrides cgroup CPU quota.
+    pub(crate) fn set_num_cpus(&mut self, num_cpus: usize) {
+        self.num_cpus = clamp_num_cpus(num_cpus);
+        self.cpu_quota = None;
+    }
+    fn current_tid(&self) -> Option<Pid> {
+        self.tid_source.map(|f| f()).or_else(gettid)
+    }
+    /// Set metadata that is attached to every serialized report
+    ///
+    /// Entries are emitted under the `meta` key by `report_json()` and
+    /// `report_json_str()`, `Report` itself doesn't carry them.
+    /// This is useful for stamping reports with application version,
+    /// hostname and similar things for log correlation. When metadata is
+    /// non-empty, `report_json()` also includes `config()` under `config`
+    /// key.
+    pub fn set_metadata(&mut self, map: BTreeMap<String, String>) {
+        self.metadata = map;
+    }
+    /// Limit how often the `scan_and_notify()` callback fires
+    ///
+    /// Scans still happen at full frequency (so rates are accurate), but
+    /// the callback is called at most once per `window`, measured between
+    /// the scans. This decouples measurement cadence from reporting
+    /// cadence.
+    pub fn set_report_throttle(&mut self, window: Duration) {
+        self.report_throttle = Some(window);
+    }
+    /// Returns true if report of the latest scan may be emitted
+    ///
+    /// Records the scan as emitted, so further calls return false until
+    /// a scan outside of the report throttle window happens.
+    pub(crate) fn take_emit_slot(&mut self) -> bool {
+        let instant = match self.snapshots.back() {
+            Some(snap) => snap.instant,
+            None => return false,
+        };
+        if let (Some(window), Some(last_emit))
+            = (self.report_throttle, self.last_emit)
+        {
+            if instant.duration_since(last_emit) < window {
+                return false;
+            }
+        }
+        self.last_emit = Some(instant);
+        true
+    }
+    /// Use current IO counters as a zero point for `*_since_baseline`
+    ///
+    /// Counters are taken from the latest scan, if there were no scans yet
+    /// they are captured on the next one. This allows to account IO of
+    /// some request or task in a long-lived process.
+    pub fn reset_io_baseline(&mut self) {
+        match self.snapshots.back() {
+            Some(snap) => self.io_baseline = IoBaseline::from_snapshot(snap),
+            None => self.io_baseline_pending = true,
+        }
+    }
+    /// Reset all peak values, so they track peaks since this call
+    ///
+    /// This resets peaks of memory, swap, CPU usage, number of threads and
+    /// file descriptors, they are reseeded by the next scan. Useful to
+    /// report peaks for each hour or other period of a long-running
+    /// service. Note: `memory_virtual_peak` is tracked by the OS and can't
+    /// be reset.
+    pub fn reset_peaks(&mut self) {
+        self.memory_rss_peak = 0;
+        self.memory_swap_peak = 0;
+        self.process_cpu_peak = 0.;
+        self.num_threads_peak = 0;
+        self.fd_count_peak = 0;
+        self.reset_smoothing();
+    }
+    /// Returns fraction of wall time the meter has spent scanning
+    ///
+    /// This is the total duration of all `scan()` calls divided by time
+    /// elapsed since the meter was created. I.e. the observability tax
+    /// of this library, where `0.001` means 0.1% of wall-clock time spent
+    /// inside `scan()`. Time blocked on reading /proc counts too, so this
+    /// is not CPU time.
+    pub fn self_overhead_fraction(&self) -> f32 {
+        let elapsed = self.created.elapsed().as_secs_f32();
+        if elapsed > 0. {
+            self.scan_time.as_secs_f32() / elapsed
+        } else {
+            0.
+        }
+    }
+    /// Returns true if internal buffers had to grow beyond initial capacity
+    ///
+    /// Meter preallocates buffers for reading /proc files to avoid memory
+    /// allocations on each scan. If system's files are unexpectedly large,
+    /// buffers are reallocated (once, they are reused afterwards). This
+    /// allows to detect that case when tuning for zero allocations.
+    pub fn buffer_grew(&self) -> bool {
+        self.text_buf.capacity() > TEXT_BUF_CAPACITY ||
+            self.path_buf.capacity() > PATH_BUF_CAPACITY
+    }
+    /// Returns statistics of intervals between scans vs `scan_interval`
+    ///
+    /// High jitter (like when scanning from a busy event loop) degrades
+    /// accuracy of the metrics, you may want to scan in a dedicated thread
+    /// in that case.
+    pub fn scan_jitter(&self) -> JitterStats {
+        JitterStats {
+            samples: self.jitter_samples,
+            mean_deviation: self.jitter_total.as_nanos()
+                .checked_div(u128::from(self.jitter_samples))
+                .map(|nanos| Duration::from_nanos(nanos as u64))
+                .unwrap_or(Duration::new(0, 0)),
+            max_deviation: self.jitter_max,
+        }
+    }
+    /// Pause scanning, `scan()` is a no-op until `resume()` is called
+    ///
+    /// This allows to get rid of measurement overhead when process is idle.
+    /// Reports are still available for intervals scanned before the pause.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+    /// Resume scanning after `pause()`
+    ///
+    /// Snapshots taken before the pause are discarded (also for tracked
+    /// processes), so the first report after resume doesn't span the idle
+    /// gap. As always, two scans are needed before the next report.
+    pub fn resume(&mut self) {
+        if !self.paused {
+            return;
+        }
+        self.paused = false;
+        self.snapshots.clear();
+        self.reset_smoothing();
+        for meter in self.processes.values_mut() {
+            meter.snapshots.clear();
+        }
+    }
+    /// Discard all snapshots, so the next report starts fresh
+    ///
+    /// Tracked threads and processes, open files and configuration are
+    /// kept. As after construction, two scans are needed for the next
+    /// `report()`. Peaks are preserved unless `reset_peaks()` is also
+    /// called.
+    pub fn clear_history(&mut self) {
+        self.snapshots.clear();
+        for meter in self.processes.values_mut() {
+            meter.snapshots.clear();
+        }
+        self.reset_smoothing();
+    }
+    /// Returns true if scanning is paused with `pause()`
+    pub fn is_paused(&self) -> bool {
+        self.paused
     }
     /// Returns interval value configured in constructor
     pub fn get_scan_interval(&self) -> Duration {
         self.scan_interval
     }
+    /// Returns wall clock time of the latest scan, `None` if no scans yet
+    pub fn last_snapshot_time(&self) -> Option<SystemTime> {
+        self.snapshots.back().map(|snap| snap.timestamp)
+    }
+    /// Returns number of snapshots currently kept in history
+    ///
+    /// This is at most `num_snapshots` of `MeterBuilder`. Reports need two.
+    pub fn snapshot_count(&self) -> usize {
+        self.snapshots.len()
+    }
+    /// Returns raw `(uptime, idle_time)` of the latest scan
+    ///
+    /// Both are in centiseconds as read from /proc/uptime (idle time is
+    /// summed across cores). This is useful to diagnose sampling problems.
+    /// Returns `None` if no scans yet.
+    pub fn raw_uptime(&self) -> Option<(u64, u64)> {
+        self.snapshots.back().map(|snap| (snap.uptime, snap.idle_time))
+    }
+}
+
+impl IoBaseline {
+    fn new() -> IoBaseline {
+        IoBaseline {
+            read_bytes: 0,
+            write_bytes: 0,
+            read_disk_bytes: 0,
+            write_disk_bytes: 0,
+        }
+    }
+    pub fn from_snapshot(snap: &Snapshot) -> IoBaseline {
+        IoBaseline {
+            read_bytes: snap.read_bytes,
+            write_bytes: snap.write_bytes,
+            read_disk_bytes: snap.read_disk_bytes,
+            write_disk_bytes: snap.write_disk_bytes,
+        }
+    }
+}
+
+#[cfg(target_os="linux")]
+fn gettid() -> Option<Pid> {
+    use libc::{syscall, SYS_gettid};
+    Some(unsafe { syscall(SYS_gettid) } as Pid)
+}
+
+#[cfg(not(target_os="linux"))]
+fn gettid() -> Option<Pid> {
+    // TODO(tailhook) OS X and windows
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use std::collections::HashMap;
+
+    use {Meter, Pid, Snapshot};
+
+    fn fake_tid() -> Pid {
+        42
+    }
+
+    #[test]
+    fn track_current_thread() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        meter.set_tid_source(fake_tid);
+        assert_eq!(meter.track_current_thread("main"), 42);
+        assert_eq!(meter.thread_names.get(&42).map(|x| &x[..]), Some("main"));
+        meter.untrack_current_thread();
+        assert!(meter.thread_names.is_empty());
+    }
+
+    #[test]
+    fn pause_resume() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        meter.snapshots.push_back(Snapshot::new(&HashMap::new()));
+        meter.snapshots.push_back(Snapshot::new(&HashMap::new()));
+        meter.pause();
+        assert!(meter.is_paused());
+        meter.scan().unwrap();
+        assert_eq!(meter.snapshots.len(), 2);
+        assert!(meter.report().is_some());
+        meter.resume();
+        assert!(!meter.is_paused());
+        assert!(meter.report().is_none());
+    }
+
+    #[test]
+    fn reset_peaks() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        let mut snap = Snapshot::new(&HashMap::new());
+        snap.memory_rss = 1000;
+        snap.fd_count = 100;
+        meter.update_peaks(&snap);
+        assert_eq!(meter.memory_rss_peak, 1000);
+        meter.reset_peaks();
+        assert_eq!(meter.memory_rss_peak, 0);
+        snap.memory_rss = 300;
+        snap.fd_count = 10;
+        meter.update_peaks(&snap);
+        assert_eq!(meter.memory_rss_peak, 300);
+        assert_eq!(meter.fd_count_peak, 10);
+    }
+
+    #[test]
+    fn clear_history() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        meter.snapshots.push_back(Snapshot::new(&HashMap::new()));
+        meter.snapshots.push_back(Snapshot::new(&HashMap::new()));
+        meter.memory_rss_peak = 1000;
+        assert!(meter.report().is_some());
+        meter.clear_history();
+        assert_eq!(meter.snapshot_count(), 0);
+        assert!(meter.report().is_none());
+        assert_eq!(meter.memory_rss_peak, 1000);
+    }
+
+    #[test]
+    fn report_throttle() {
+        use report::test::{snapshot, meter_with};
+
+        let mut meter = meter_with(snapshot(100, 0), snapshot(200, 0));
+        // without throttle every call is allowed
+        assert!(meter.take_emit_slot());
+        assert!(meter.take_emit_slot());
+        meter.set_report_throttle(Duration::new(10, 0));
+        // the latest scan is emitted already
+        assert!(!meter.take_emit_slot());
+        let start = meter.snapshots.back().unwrap().instant;
+        for &(secs, emitted) in &[(5, false), (9, false), (10, true),
+                                  (15, false), (25, true)]
+        {
+            let mut snap = snapshot(200 + secs * 100, 0);
+            snap.instant = start + Duration::new(secs, 0);
+            meter.snapshots.push_back(snap);
+            assert_eq!(meter.take_emit_slot(), emitted, "at {}s", secs);
+        }
+    }
+
+    #[test]
+    fn jitter_many_samples() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        assert_eq!(meter.scan_jitter().mean_deviation, Duration::new(0, 0));
+        // would be truncated to 2 samples if divided as u32
+        meter.jitter_samples = (1 << 32) + 2;
+        meter.jitter_total = Duration::new(1 << 33, 0) +
+            Duration::new(4, 0);
+        assert_eq!(meter.scan_jitter().mean_deviation, Duration::new(2, 0));
+    }
 }
@@ -0,0 +1,82 @@
+use {Meter, Report, Snapshot, Error};
+
+
+impl Meter {
+    /// Start measuring a named phase of the program (e.g. load, flush)
+    ///
+    /// This takes a snapshot immediately, the matching `end_phase()` takes
+    /// another one and reports resource usage for exactly that span.
+    /// Phase snapshots are kept aside of the regular `scan()` history, so
+    /// phases don't influence `report()` and may span any number of scans.
+    ///
+    /// Starting a phase discards the previous one if it's not ended yet.
+    pub fn begin_phase(&mut self, name: &str) -> Result<(), Error> {
+        let mut snap = Snapshot::new(&self.thread_names);
+        self.take_snapshot(&mut snap)?;
+        self.phase = Some((name.to_string(), snap));
+        Ok(())
+    }
+    /// Finish the phase started by `begin_phase()` and report its usage
+    ///
+    /// Returns phase name and report, or `None` if no phase was started.
+    pub fn end_phase(&mut self) -> Result<Option<(String, Report)>, Error> {
+        let (name, start) = match self.phase.take() {
+            Some(phase) => phase,
+            None => return Ok(None),
+        };
+        let mut snap = Snapshot::new(&self.thread_names);
+        self.take_snapshot(&mut snap)?;
+        Ok(Some((name, self.report_between(&start, &snap))))
+    }
+    /// Run the closure and report resource usage of exactly that execution
+    ///
+    /// This takes a snapshot before and after running `f`, and returns the
+    /// closure's result along with the report. Like phases, these
+    /// snapshots don't affect `report()`.
+    ///
+    /// Note: CPU time is accounted by the kernel in clock ticks (usually
+    /// 10ms), so for very short closures CPU figures are inaccurate, the
+    /// longer the operation the better the accuracy.
+    pub fn measure<F, R>(&mut self, f: F) -> Result<(R, Report), Error>
+        where F: FnOnce() -> R,
+    {
+        let mut start = Snapshot::new(&self.thread_names);
+        self.take_snapshot(&mut start)?;
+        let result = f();
+        let mut end = Snapshot::new(&self.thread_names);
+        self.take_snapshot(&mut end)?;
+        Ok((result, self.report_between(&start, &end)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use Meter;
+
+    #[test]
+    fn phase() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        assert!(meter.end_phase().unwrap().is_none());
+        meter.begin_phase("load").unwrap();
+        let (name, report) = meter.end_phase().unwrap().unwrap();
+        assert_eq!(name, "load");
+        assert!(report.memory_rss > 0);
+        assert!(meter.end_phase().unwrap().is_none());
+        // phases don't touch scan history
+        assert!(meter.snapshots.is_empty());
+    }
+
+    #[test]
+    fn measure() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        let (value, report) = meter.measure(|| {
+            (0..1000u64).sum::<u64>()
+        }).unwrap();
+        assert_eq!(value, 499500);
+        assert!(report.memory_rss > 0);
+        assert!(report.process_cpu_usage.is_finite());
+        assert!(meter.snapshots.is_empty());
+    }
+}
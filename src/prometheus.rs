@@ -0,0 +1,157 @@
+use metrics::{Gauge, Unit};
+
+use {Meter, Report};
+
+
+/// Publishes reports through the `metrics` crate facade
+///
+/// This is useful with `metrics-exporter-prometheus` or any other
+/// recorder installed by the application. Bridge registers gauges once
+/// on creation and owns the handles, so `update()` is cheap. Call it with
+/// a fresh report after each `Meter::scan()`.
+///
+/// Enabled by the `metrics` feature.
+#[derive(Debug, Clone)]
+pub struct PrometheusBridge {
+    global_cpu_usage: Gauge,
+    process_cpu_usage: Gauge,
+    gross_cpu_usage: Gauge,
+    parallelism: Gauge,
+    memory_rss: Gauge,
+    memory_virtual: Gauge,
+    memory_swap: Gauge,
+    disk_read: Gauge,
+    disk_write: Gauge,
+    disk_cancelled: Gauge,
+    io_read: Gauge,
+    io_write: Gauge,
+    io_read_ops: Gauge,
+    io_write_ops: Gauge,
+    thrashing_indicator: Gauge,
+    net_read: Gauge,
+    net_write: Gauge,
+    num_threads: Gauge,
+    fd_count: Gauge,
+}
+
+impl PrometheusBridge {
+    /// Describe metrics and register gauges with the installed recorder
+    ///
+    /// Note: recorder must be installed before the bridge is created,
+    /// otherwise handles are no-op.
+    pub fn new() -> PrometheusBridge {
+        PrometheusBridge::describe();
+        PrometheusBridge::register()
+    }
+    /// Rates of bytes get `Unit::Bytes` (the `metrics` crate has no unit
+    /// for bytes per second), their names end with `_per_second`
+    fn describe() {
+        describe_gauge!("system_cpu_usage_percent", Unit::Percent,
+            "Whole system CPU usage, 100% is all cores");
+        describe_gauge!("process_cpu_usage_percent", Unit::Percent,
+            "Process' own CPU usage, 100% is a single core");
+        describe_gauge!("process_gross_cpu_usage_percent", Unit::Percent,
+            "Process' CPU usage with awaited children");
+        describe_gauge!("process_parallelism", Unit::Count,
+            "Number of cores' worth of work done per second");
+        describe_gauge!("process_memory_rss_bytes", Unit::Bytes,
+            "Process' resident memory");
+        describe_gauge!("process_memory_virtual_bytes", Unit::Bytes,
+            "Process' virtual memory");
+        describe_gauge!("process_memory_swap_bytes", Unit::Bytes,
+            "Process' swapped out memory");
+        describe_gauge!("process_disk_read_bytes_per_second", Unit::Bytes,
+            "Bytes read per second from block-backed filesystems");
+        describe_gauge!("process_disk_write_bytes_per_second", Unit::Bytes,
+            "Bytes written per second to block-backed filesystems");
+        describe_gauge!("process_disk_cancelled_bytes_per_second", Unit::Bytes,
+            "Bytes per second of cancelled writes");
+        describe_gauge!("process_io_read_bytes_per_second", Unit::Bytes,
+            "Bytes read per second (total)");
+        describe_gauge!("process_io_write_bytes_per_second", Unit::Bytes,
+            "Bytes written per second (total)");
+        describe_gauge!("process_io_read_ops_per_second",
+            Unit::CountPerSecond, "Read syscalls per second");
+        describe_gauge!("process_io_write_ops_per_second",
+            Unit::CountPerSecond, "Write syscalls per second");
+        describe_gauge!("process_thrashing_indicator", Unit::Count,
+            "Memory thrashing score from 0 to 1");
+        describe_gauge!("process_net_read_bytes_per_second", Unit::Bytes,
+            "Bytes received per second in the network namespace");
+        describe_gauge!("process_net_write_bytes_per_second", Unit::Bytes,
+            "Bytes sent per second in the network namespace");
+        describe_gauge!("process_threads", Unit::Count,
+            "Number of threads in the process");
+        describe_gauge!("process_open_fds", Unit::Count,
+            "Number of open file descriptors");
+    }
+    /// Look up gauges in the installed recorder
+    fn register() -> PrometheusBridge {
+        PrometheusBridge {
+            global_cpu_usage: gauge!("system_cpu_usage_percent"),
+            process_cpu_usage: gauge!("process_cpu_usage_percent"),
+            gross_cpu_usage: gauge!("process_gross_cpu_usage_percent"),
+            parallelism: gauge!("process_parallelism"),
+            memory_rss: gauge!("process_memory_rss_bytes"),
+            memory_virtual: gauge!("process_memory_virtual_bytes"),
+            memory_swap: gauge!("process_memory_swap_bytes"),
+            disk_read: gauge!("process_disk_read_bytes_per_second"),
+            disk_write: gauge!("process_disk_write_bytes_per_second"),
+            disk_cancelled: gauge!("process_disk_cancelled_bytes_per_second"),
+            io_read: gauge!("process_io_read_bytes_per_second"),
+            io_write: gauge!("process_io_write_bytes_per_second"),
+            io_read_ops: gauge!("process_io_read_ops_per_second"),
+            io_write_ops: gauge!("process_io_write_ops_per_second"),
+            thrashing_indicator: gauge!("process_thrashing_indicator"),
+            net_read: gauge!("process_net_read_bytes_per_second"),
+            net_write: gauge!("process_net_write_bytes_per_second"),
+            num_threads: gauge!("process_threads"),
+            fd_count: gauge!("process_open_fds"),
+        }
+    }
+    /// Set all gauges to the values of the report
+    pub fn update(&self, report: &Report) {
+        self.global_cpu_usage.set(report.global_cpu_usage);
+        self.process_cpu_usage.set(report.process_cpu_usage);
+        self.gross_cpu_usage.set(report.gross_cpu_usage);
+        self.parallelism.set(report.parallelism);
+        self.memory_rss.set(report.memory_rss as f64);
+        self.memory_virtual.set(report.memory_virtual as f64);
+        self.memory_swap.set(report.memory_swap as f64);
+        self.disk_read.set(report.disk_read);
+        self.disk_write.set(report.disk_write);
+        self.disk_cancelled.set(report.disk_cancelled);
+        self.io_read.set(report.io_read);
+        self.io_write.set(report.io_write);
+        self.io_read_ops.set(report.io_read_ops);
+        self.io_write_ops.set(report.io_write_ops);
+        self.thrashing_indicator.set(report.thrashing_indicator);
+        self.net_read.set(report.net_read);
+        self.net_write.set(report.net_write);
+        self.num_threads.set(report.num_threads as f64);
+        self.fd_count.set(report.fd_count as f64);
+    }
+}
+
+impl Meter {
+    /// Publish latest report through the `metrics` crate facade
+    ///
+    /// Publishes the same metrics as `PrometheusBridge::update`, but looks
+    /// up gauges by name on each call, so it's a bit slower but needs no
+    /// state. Metrics aren't described, create a `PrometheusBridge` once
+    /// if the exporter needs descriptions and units. Does nothing if there
+    /// are not enough scans for a report yet.
+    ///
+    /// Enabled by the `metrics` feature.
+    pub fn emit_metrics(&self) {
+        if let Some(report) = self.report() {
+            PrometheusBridge::register().update(&report);
+        }
+    }
+}
+
+impl Default for PrometheusBridge {
+    fn default() -> PrometheusBridge {
+        PrometheusBridge::new()
+    }
+}
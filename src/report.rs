@@ -1,7 +1,14 @@
+use std::cmp::{max, Ordering};
 use std::time::{Duration};
 use std::collections::hash_map::Iter;
+#[cfg(feature="serde")] use std::collections::BTreeMap;
 
-use {Pid, Meter, Report, Snapshot, ThreadReport};
+#[cfg(feature="serde")] use serde_json::{self, Value};
+
+use {Pid, Meter, Report, Snapshot, ThreadReport, AggregateReport, CpuSource};
+use ThreadInfo;
+use builder::WarmupBehavior;
+#[cfg(feature="serde")] use MeterConfig;
 
 
 /// Iterator over thread reports returned by ``Meter::thread_report``
@@ -12,66 +19,362 @@ pub struct ThreadReportIter<'a> {
     centisecs: f32,
 }
 
+/// Report as serialized by ``Meter::report_json``
+#[cfg(feature="serde")]
+#[derive(Serialize)]
+struct ReportWithConfig<'a> {
+    #[serde(flatten)]
+    report: &'a Report,
+    #[serde(skip_serializing_if="BTreeMap::is_empty")]
+    meta: &'a BTreeMap<String, String>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    config: Option<MeterConfig>,
+}
+
+/// Iterator over historical reports returned by ``Meter::report_history``
+pub struct ReportHistoryIter<'a> {
+    meter: &'a Meter,
+    index: usize,
+}
+
+/// Major faults per second which count as full score for thrashing
+const THRASHING_MAJOR_FAULTS: f32 = 100.0;
+/// Swapped in pages per second which count as full score for thrashing
+const THRASHING_SWAP_IN: f32 = 100.0;
+/// Weight of process' major faults in thrashing indicator
+const THRASHING_FAULTS_WEIGHT: f32 = 0.75;
+/// Weight of system-wide swap-in in thrashing indicator
+const THRASHING_SWAP_IN_WEIGHT: f32 = 0.25;
+
+/// Wall clock time exceeding uptime delta this many times means suspend
+const SUSPEND_RATIO: u32 = 2;
+/// Minimum difference between wall clock and uptime deltas for suspend
+const SUSPEND_MIN_GAP: Duration = Duration::from_secs(1);
+
+/// Iterator over process reports returned by ``Meter::process_reports``
+pub struct ProcessReportIter<'a> {
+    processes: Iter<'a, Pid, Meter>,
+}
+
+/// Clock ticks of the process including awaited children
+///
+/// Counters are saturated rather than wrapped, so the result is sane even
+/// with bogus values in /proc
+fn gross_cpu_time(info: &ThreadInfo) -> u64 {
+    info.user_time
+        .saturating_add(info.system_time)
+        .saturating_add(info.child_user_time)
+        .saturating_add(info.child_system_time)
+}
+
+/// Returns the length of interval between snapshots in centiseconds
+///
+/// Uptime is used normally, as CPU times are measured in the same
+/// (scheduler) clock. But if both scans happened within the same
+/// centisecond of uptime, monotonic clock is used to avoid division by zero.
+fn interval_centisecs(prev: &Snapshot, last: &Snapshot) -> f32 {
+    let uptime = last.uptime.saturating_sub(prev.uptime);
+    if uptime > 0 {
+        uptime as f32
+    } else {
+        let elapsed = last.instant.saturating_duration_since(prev.instant);
+        elapsed.as_secs_f32() * 100.
+    }
+}
+
+/// Returns true if wall clock advanced much more than uptime between scans
+fn was_suspended(prev: &Snapshot, last: &Snapshot) -> bool {
+    let wall = match last.timestamp.duration_since(prev.timestamp) {
+        Ok(wall) => wall,
+        // wall clock went backwards, that's not a suspend
+        Err(_) => return false,
+    };
+    // uptime is in centiseconds
+    let uptime = duration_from_ms(
+        last.uptime.saturating_sub(prev.uptime).saturating_mul(10));
+    wall > uptime.saturating_mul(SUSPEND_RATIO) &&
+        wall - uptime >= SUSPEND_MIN_GAP
+}
+
 fn duration_from_ms(ms: u64) -> Duration {
-    Duration::new(ms / 1000, ((ms % 1000) * 1000_000) as u32)
+    Duration::new(ms / 1000, ((ms % 1000) * 1_000_000) as u32)
 }
 
 
+/// Collects thread reports sorted by descending CPU usage, then by name
+fn sorted_threads(iter: ThreadReportIter) -> Vec<(String, ThreadReport)> {
+    let mut threads = iter
+        .map(|(name, report)| (name.to_string(), report))
+        .collect::<Vec<_>>();
+    threads.sort_by(|a, b| {
+        b.1.cpu_usage.partial_cmp(&a.1.cpu_usage)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| a.0.cmp(&b.0))
+    });
+    threads
+}
+
 impl Meter {
     /// Get report of the last scan interval
     ///
     /// We need at least two scans to measure CPU usage, so this method
-    /// returns None if less than two scans were done ever in the past.
+    /// returns None if less than two scans were done ever in the past,
+    /// unless `WarmupBehavior::ZeroReport` is set in `MeterBuilder`.
     pub fn report(&self) -> Option<Report> {
-        if self.snapshots.len() < 2 {
-            return None;
+        match self.snapshots.len() {
+            0 => None,
+            1 if self.warmup_behavior == WarmupBehavior::ZeroReport => {
+                let snap = &self.snapshots[0];
+                Some(self.report_between(snap, snap))
+            }
+            1 => None,
+            n => Some(self.report_between(&self.snapshots[n-2],
+                                          &self.snapshots[n-1])),
         }
+    }
+    /// Computes `Report::process_cpu_usage` between two snapshots
+    pub(crate) fn process_cpu_between(&self, prev: &Snapshot, last: &Snapshot)
+        -> f32
+    {
+        let centisecs = interval_centisecs(prev, last);
+        if centisecs <= 0. {
+            return 0.;
+        }
+        let (lpro, ppro) = (&last.process, &prev.process);
+        let ticks = lpro.user_time.saturating_add(lpro.system_time)
+            .saturating_sub(ppro.user_time.saturating_add(ppro.system_time));
+        (100.0 * ticks as f32 / centisecs)
+            .clamp(0., self.effective_cpus() * 100.)
+    }
+    /// Get report averaged over the retained scans within the `window`
+    ///
+    /// Deltas are computed between the newest snapshot and the oldest one
+    /// which is not older than `window`, so this smooths out spikes
+    /// compared to `report()`. Number of snapshots kept (see
+    /// `MeterBuilder::num_snapshots`) limits the widest window available.
+    ///
+    /// Returns `None` if there is no previous snapshot within the window.
+    pub fn report_over(&self, window: Duration) -> Option<Report> {
+        let last = self.snapshots.back()?;
         let n = self.snapshots.len();
-        let last = &self.snapshots[n-1];
-        let prev = &self.snapshots[n-2];
+        let prev = self.snapshots.iter().take(n-1)
+            .find(|snap| {
+                last.instant.saturating_duration_since(snap.instant) <= window
+            })?;
+        Some(self.report_between(prev, last))
+    }
+    /// Computes report for the interval between two snapshots
+    pub(crate) fn report_between(&self, prev: &Snapshot, last: &Snapshot)
+        -> Report
+    {
         let lpro = &last.process;
         let ppro = &prev.process;
-        let centisecs = (last.uptime - prev.uptime) as f32;
-        let secs = centisecs / 100.0;
-        let mut cpu_usage = 100.0 * (1.0 -
-                (last.idle_time - prev.idle_time) as f32 /
-                (centisecs * self.num_cpus as f32));
-        if cpu_usage < 0. {  // sometimes we get inaccuracy
-            cpu_usage = 0.;
-        }
-        Some(Report {
+        let centisecs = interval_centisecs(prev, last);
+        // interval is empty for a warm-up report, all rates are zero then
+        let per_centisec = if centisecs > 0. { 1. / centisecs } else { 0. };
+        let per_sec = 100. * per_centisec;
+        let num_cpus = max(self.num_cpus, 1) as f32;
+        let stat_total = last.stat_total.saturating_sub(prev.stat_total);
+        let stat_percent = |last: u64, prev: u64| if stat_total > 0 {
+            100.0 * last.saturating_sub(prev) as f32 / stat_total as f32
+        } else {
+            0.
+        };
+        let (cpu_usage, cpu_source) = if stat_total > 0 {
+            let idle = last.stat_idle.saturating_sub(prev.stat_idle);
+            (100.0 * (1.0 - idle as f32 / stat_total as f32),
+             CpuSource::ProcStat)
+        } else {
+            // /proc/stat is unavailable, fall back to the less precise
+            // idle time of /proc/uptime
+            let usage = last.idle_time.checked_sub(prev.idle_time)
+                .filter(|_| centisecs > 0.)
+                .map(|idle| {
+                    100.0 * (1.0 - idle as f32 / (centisecs * num_cpus))
+                })
+                // sometimes we get inaccuracy
+                .filter(|usage| (0. ..=100.).contains(usage))
+                .unwrap_or(0.);
+            (usage, CpuSource::Uptime)
+        };
+        let udelta = lpro.user_time.saturating_sub(ppro.user_time);
+        let sdelta = lpro.system_time.saturating_sub(ppro.system_time);
+        let process_cpu_usage = self.process_cpu_between(prev, last);
+        let gross_cpu_usage = if self.include_child_cpu {
+            100.0 *
+            gross_cpu_time(lpro).saturating_sub(gross_cpu_time(ppro)) as f32 *
+            per_centisec
+        } else {
+            process_cpu_usage
+        };
+        let effective_cpus = self.effective_cpus();
+        let parallelism = (gross_cpu_usage / 100.0)
+            .max(0.).min(effective_cpus);
+        let gross_cpu_usage =
+            gross_cpu_usage.clamp(0., effective_cpus * 100.);
+        let faults = lpro.major_faults
+            .saturating_sub(ppro.major_faults) as f32 * per_sec;
+        let swap_in = last.swap_in_pages
+            .saturating_sub(prev.swap_in_pages) as f32 * per_sec;
+        let io_read = last.read_bytes.saturating_sub(prev.read_bytes);
+        let io_write = last.write_bytes.saturating_sub(prev.write_bytes);
+        let thrashing_indicator =
+            THRASHING_FAULTS_WEIGHT *
+                (faults / THRASHING_MAJOR_FAULTS).min(1.) +
+            THRASHING_SWAP_IN_WEIGHT *
+                (swap_in / THRASHING_SWAP_IN).min(1.);
+        Report {
             timestamp: last.timestamp,
             duration: last.instant - prev.instant,
             start_time: self.start_time,
-            system_uptime: duration_from_ms(last.uptime * 10),  // centisecs
+            // uptime is in centiseconds
+            system_uptime: duration_from_ms(last.uptime.saturating_mul(10)),
             global_cpu_usage: cpu_usage,
-            process_cpu_usage: 100.0 *
-                (lpro.user_time + lpro.system_time -
-                 (ppro.user_time + ppro.system_time)) as f32 / centisecs,
-            gross_cpu_usage: 100.0 *
-                ((lpro.user_time  + lpro.system_time +
-                  lpro.child_user_time + lpro.child_system_time) -
-                 (ppro.user_time + ppro.system_time +
-                  ppro.child_user_time + ppro.child_system_time)) as f32 /
-                centisecs,
+            global_cpu_source: cpu_source,
+            global_iowait: stat_percent(last.stat_iowait, prev.stat_iowait),
+            global_steal: stat_percent(last.stat_steal, prev.stat_steal),
+            global_guest: stat_percent(last.stat_guest, prev.stat_guest),
+            process_cpu_usage,
+            process_cpu_peak: self.process_cpu_peak.max(process_cpu_usage),
+            process_user_cpu: 100.0 * udelta as f32 * per_centisec,
+            process_system_cpu: 100.0 * sdelta as f32 * per_centisec,
+            kernel_user_ratio: if udelta > 0 {
+                sdelta as f32 / udelta as f32
+            } else {
+                0.
+            },
+            gross_cpu_usage,
+            parallelism,
             memory_rss: last.memory_rss,
             memory_virtual: last.memory_virtual,
             memory_swap: last.memory_swap,
+            memory_rss_anon: last.memory_rss_anon,
+            memory_rss_file: last.memory_rss_file,
+            memory_rss_shmem: last.memory_rss_shmem,
+            memory_data: last.memory_data,
+            memory_stack: last.memory_stack,
+            memory_exe: last.memory_exe,
+            memory_lib: last.memory_lib,
             memory_rss_peak: self.memory_rss_peak,
+            memory_rss_hwm: last.memory_rss_hwm,
             memory_virtual_peak: last.memory_virtual_peak,
             memory_swap_peak: self.memory_swap_peak,
-            disk_read: (last.read_disk_bytes - prev.read_disk_bytes) as f32
-                / secs,
-            disk_write: (last.write_disk_bytes - prev.write_disk_bytes) as f32
-                / secs,
-            disk_cancelled: (last.write_cancelled_bytes -
-                             prev.write_cancelled_bytes) as f32 / secs,
-            io_read: (last.read_bytes - prev.read_bytes) as f32 / secs,
-            io_write: (last.write_bytes - prev.write_bytes) as f32 / secs,
-            io_read_ops: (last.read_ops - prev.read_ops) as f32 / secs,
-            io_write_ops: (last.write_ops - prev.write_ops) as f32 / secs,
+            system_memory_total: last.system_memory_total,
+            system_memory_available: last.system_memory_available,
+            system_memory_free: last.system_memory_free,
+            memory_limit: self.memory_limit,
+            num_threads: last.num_threads,
+            num_threads_peak: self.num_threads_peak,
+            fd_count: last.fd_count,
+            fd_count_peak: self.fd_count_peak,
+            voluntary_ctxt_switches: last.voluntary_ctxt_switches
+                .saturating_sub(prev.voluntary_ctxt_switches) as f32 * per_sec,
+            involuntary_ctxt_switches: last.nonvoluntary_ctxt_switches
+                .saturating_sub(prev.nonvoluntary_ctxt_switches) as f32
+                * per_sec,
+            disk_read: last.read_disk_bytes
+                .saturating_sub(prev.read_disk_bytes) as f32 * per_sec,
+            disk_write: last.write_disk_bytes
+                .saturating_sub(prev.write_disk_bytes) as f32 * per_sec,
+            disk_cancelled: last.write_cancelled_bytes
+                .saturating_sub(prev.write_cancelled_bytes) as f32 * per_sec,
+            io_read: io_read as f32 * per_sec,
+            io_write: io_write as f32 * per_sec,
+            io_read_ops: last.read_ops.saturating_sub(prev.read_ops) as f32
+                * per_sec,
+            io_write_ops: last.write_ops.saturating_sub(prev.write_ops) as f32
+                * per_sec,
+            net_read: last.net_rx_bytes
+                .saturating_sub(prev.net_rx_bytes) as f32 * per_sec,
+            net_write: last.net_tx_bytes
+                .saturating_sub(prev.net_tx_bytes) as f32 * per_sec,
+            net_read_packets: last.net_rx_packets
+                .saturating_sub(prev.net_rx_packets) as f32 * per_sec,
+            net_write_packets: last.net_tx_packets
+                .saturating_sub(prev.net_tx_packets) as f32 * per_sec,
+            io_read_total_since_baseline:
+                last.read_bytes.saturating_sub(self.io_baseline.read_bytes),
+            io_write_total_since_baseline:
+                last.write_bytes.saturating_sub(self.io_baseline.write_bytes),
+            disk_read_total_since_baseline: last.read_disk_bytes
+                .saturating_sub(self.io_baseline.read_disk_bytes),
+            disk_write_total_since_baseline: last.write_disk_bytes
+                .saturating_sub(self.io_baseline.write_disk_bytes),
+            io_read_write_ratio: if io_write > 0 {
+                Some(io_read as f32 / io_write as f32)
+            } else {
+                None
+            },
+            cache_hit_ratio: if io_read > 0 {
+                let disk = last.read_disk_bytes
+                    .saturating_sub(prev.read_disk_bytes);
+                Some((1.0 - disk as f32 / io_read as f32).clamp(0., 1.))
+            } else {
+                None
+            },
+            minor_faults: lpro.minor_faults
+                .saturating_sub(ppro.minor_faults) as f32 * per_sec,
+            major_faults: faults,
+            thrashing_indicator,
+            is_traced: prev.tracer_pid != 0 || last.tracer_pid != 0,
+            suspended_during_interval: was_suspended(prev, last),
+        }
+    }
+    /// Get report of the last scan interval as JSON value
+    ///
+    /// Returns `None` when `report()` does. Metadata set with
+    /// `set_metadata()` is included under `meta` key and, if it's
+    /// non-empty, `config()` is included under `config` key.
+    #[cfg(feature="serde")]
+    pub fn report_json(&self) -> Option<Value> {
+        self.report().map(|report| {
+            serde_json::to_value(self.with_config(&report))
+                .expect("report is always serializable")
+        })
+    }
+    /// Get report of the last scan interval serialized to JSON string
+    ///
+    /// Returns `None` when `report()` does. Includes `meta` and `config`
+    /// the same way as `report_json()`.
+    #[cfg(feature="serde")]
+    pub fn report_json_str(&self) -> Option<String> {
+        self.report().map(|report| {
+            serde_json::to_string(&self.with_config(&report))
+                .expect("report is always serializable")
         })
     }
+    #[cfg(feature="serde")]
+    fn with_config<'a>(&'a self, report: &'a Report)
+        -> ReportWithConfig<'a>
+    {
+        ReportWithConfig {
+            report,
+            meta: &self.metadata,
+            config: if self.metadata.is_empty() {
+                None
+            } else {
+                Some(self.config())
+            },
+        }
+    }
+    /// Returns iterator over reports of all retained scan intervals
+    ///
+    /// Reports are yielded oldest to newest, each one is computed between
+    /// two consecutive snapshots. Up to `num_snapshots - 1` reports are
+    /// available, the last one equals to `report()`.
+    pub fn report_history(&self) -> ReportHistoryIter<'_> {
+        ReportHistoryIter {
+            meter: self,
+            index: 1,
+        }
+    }
+    /// Returns all reports of `report_history` as an indexable collection
+    ///
+    /// Index zero is the oldest report and the last element is the newest
+    /// one (same as `report()`). This is convenient for feeding a chart.
+    pub fn report_slice(&self) -> Vec<Report> {
+        self.report_history().collect()
+    }
     /// Returns iterator over reports for threads
     ///
     /// Note: each thread must be registered with `Meter::track_thread` or
@@ -79,27 +382,237 @@ impl Meter {
     ///
     /// We need at least two scans to measure CPU usage, so this method
     /// returns None if less than two scans were done ever in the past.
-    pub fn thread_report(&self) -> Option<ThreadReportIter> {
+    pub fn thread_report(&self) -> Option<ThreadReportIter<'_>> {
+        if self.snapshots.len() < 2 {
+            return None;
+        }
+        let n = self.snapshots.len();
+        Some(self.thread_report_between(&self.snapshots[n-2],
+                                        &self.snapshots[n-1]))
+    }
+    fn thread_report_between<'a>(&'a self, prev: &'a Snapshot,
+        last: &'a Snapshot)
+        -> ThreadReportIter<'a>
+    {
+        ThreadReportIter {
+            threads: self.thread_names.iter(),
+            last,
+            prev,
+            centisecs: interval_centisecs(prev, last),
+        }
+    }
+    /// Returns reports for threads sorted by descending CPU usage
+    ///
+    /// Threads with equal CPU usage are sorted by name, so output is stable
+    /// across scans unlike the order of `thread_report()` iterator (which
+    /// is cheaper as it doesn't allocate). Returns `None` under the same
+    /// conditions as `thread_report()`.
+    pub fn thread_report_sorted(&self) -> Option<Vec<(String, ThreadReport)>>
+    {
+        self.thread_report().map(sorted_threads)
+    }
+    /// Returns process report and thread reports of the last interval
+    ///
+    /// Both are computed from the same pair of snapshots, so thread numbers
+    /// are always consistent with process totals. Threads are sorted as
+    /// in `thread_report_sorted()`. Returns `None` if there were less than
+    /// two scans (regardless of `WarmupBehavior`).
+    pub fn full_report(&self) -> Option<(Report, Vec<(String, ThreadReport)>)>
+    {
+        if self.snapshots.len() < 2 {
+            return None;
+        }
+        let n = self.snapshots.len();
+        let (prev, last) = (&self.snapshots[n-2], &self.snapshots[n-1]);
+        let report = self.report_between(prev, last);
+        let threads = sorted_threads(self.thread_report_between(prev, last));
+        Some((report, threads))
+    }
+    /// Returns busy percentage of each online core over the last interval
+    ///
+    /// Vector has an element per online core (in order of core numbers),
+    /// each is from 0 to 100%. This is kept separate from `report()` to
+    /// keep the common report small. Returns `None` if there were less than
+    /// two scans.
+    pub fn per_core_report(&self) -> Option<Vec<f32>> {
         if self.snapshots.len() < 2 {
             return None;
         }
         let n = self.snapshots.len();
         let last = &self.snapshots[n-1];
         let prev = &self.snapshots[n-2];
-        let centisecs = (last.uptime - prev.uptime) as f32;
-        Some(ThreadReportIter {
-            threads: self.thread_names.iter(),
-            last: last,
-            prev: prev,
-            centisecs: centisecs,
-        })
+        Some(last.cores.iter().map(|core| {
+            // cores may go online and offline between scans
+            let pcore = match prev.cores.iter().find(|c| c.id == core.id) {
+                Some(pcore) => pcore,
+                None => return 0.,
+            };
+            let total = core.total.saturating_sub(pcore.total);
+            let idle = core.idle.saturating_sub(pcore.idle);
+            if total > 0 {
+                100.0 * (1.0 - idle as f32 / total as f32)
+            } else {
+                0.
+            }
+        }).collect())
+    }
+    /// Returns sum of CPU usage of tracked threads matching the predicate
+    ///
+    /// Predicate receives thread name as passed to `track_thread`. Returns
+    /// `None` if there is no thread report yet, see `thread_report`.
+    pub fn cpu_usage_for<F: Fn(&str) -> bool>(&self, pred: F) -> Option<f32>
+    {
+        Some(self.thread_report()?
+            .filter(|&(name, _)| pred(name))
+            .map(|(_, rep)| rep.cpu_usage)
+            .sum())
+    }
+    /// Returns process' CPU usage minus the threads matching the predicate
+    ///
+    /// This is the complement of `cpu_usage_for`, useful to subtract known
+    /// idle or GC threads to get CPU spent on useful work.
+    pub fn cpu_usage_excluding<F: Fn(&str) -> bool>(&self, pred: F)
+        -> Option<f32>
+    {
+        let excluded = self.cpu_usage_for(pred)?;
+        let report = self.report()?;
+        Some((report.process_cpu_usage - excluded).max(0.))
+    }
+    /// Returns total CPU time (user + system) the process has consumed
+    ///
+    /// This is a cumulative value since the process start taken from the
+    /// latest scan (children are not included). Returns `None` if no scan
+    /// has been done yet.
+    pub fn total_cpu_time(&self) -> Option<Duration> {
+        let last = self.snapshots.back()?;
+        let ticks = last.process.user_time
+            .saturating_add(last.process.system_time);
+        // ticks are centiseconds
+        Some(duration_from_ms(ticks.saturating_mul(10)))
+    }
+    /// Returns total CPU time (user + system) of the awaited children
+    ///
+    /// Kernel accumulates CPU time of children when they are reaped with
+    /// `wait()`, so this grows monotonically and includes all the short
+    /// lived helpers spawned since the process start. Children which are
+    /// still running are not included. Returns `None` if no scan has been
+    /// done yet.
+    pub fn child_cpu_time(&self) -> Option<Duration> {
+        let last = self.snapshots.back()?;
+        let ticks = last.process.child_user_time
+            .saturating_add(last.process.child_system_time);
+        // ticks are centiseconds
+        Some(duration_from_ms(ticks.saturating_mul(10)))
+    }
+    /// Returns time since the process was started
+    ///
+    /// This is computed from system uptime and process' start time after
+    /// boot, both from the latest scan, so unlike wall clock it isn't
+    /// affected by clock adjustments (but time the system was suspended
+    /// isn't included). Returns `None` if no scan has been done yet.
+    pub fn process_age(&self) -> Option<Duration> {
+        let last = self.snapshots.back()?;
+        let age = last.uptime.saturating_sub(last.process.start_time);
+        // both are in centiseconds
+        Some(duration_from_ms(age.saturating_mul(10)))
+    }
+    /// Returns total bytes `(read, written)` by the process since its start
+    ///
+    /// These are the cumulative counters behind `Report::io_read` and
+    /// `Report::io_write` taken from the latest scan. Returns `None` if no
+    /// scan has been done yet.
+    pub fn total_io_bytes(&self) -> Option<(u64, u64)> {
+        let last = self.snapshots.back()?;
+        Some((last.read_bytes, last.write_bytes))
+    }
+}
+
+impl Meter {
+    /// Returns iterator over reports of processes
+    ///
+    /// Note: each process must be registered with `Meter::track_process`.
+    /// Processes which don't have two scans yet are skipped.
+    pub fn process_reports(&self) -> ProcessReportIter<'_> {
+        ProcessReportIter {
+            processes: self.processes.iter(),
+        }
+    }
+    /// Returns sum of reports of all tracked processes
+    ///
+    /// Returns `None` if no tracked process has a report yet. Note this
+    /// doesn't include the process this meter is measuring itself.
+    pub fn aggregate_report(&self) -> Option<AggregateReport> {
+        let mut agg = AggregateReport {
+            processes: 0,
+            process_cpu_usage: 0.,
+            gross_cpu_usage: 0.,
+            memory_rss: 0,
+            memory_virtual: 0,
+            memory_swap: 0,
+            disk_read: 0.,
+            disk_write: 0.,
+            io_read: 0.,
+            io_write: 0.,
+        };
+        for (_, rep) in self.process_reports() {
+            agg.processes += 1;
+            agg.process_cpu_usage += rep.process_cpu_usage;
+            agg.gross_cpu_usage += rep.gross_cpu_usage;
+            agg.memory_rss += rep.memory_rss;
+            agg.memory_virtual += rep.memory_virtual;
+            agg.memory_swap += rep.memory_swap;
+            agg.disk_read += rep.disk_read;
+            agg.disk_write += rep.disk_write;
+            agg.io_read += rep.io_read;
+            agg.io_write += rep.io_write;
+        }
+        if agg.processes == 0 {
+            return None;
+        }
+        Some(agg)
+    }
+}
+
+impl<'a> Iterator for ProcessReportIter<'a> {
+    type Item = (Pid, Report);
+    fn next(&mut self) -> Option<(Pid, Report)> {
+        for (&pid, meter) in self.processes.by_ref() {
+            if let Some(report) = meter.report() {
+                return Some((pid, report));
+            }
+        }
+        None
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (_min, max) = self.processes.size_hint();
+        (0, max)
+    }
+}
+
+impl<'a> Iterator for ReportHistoryIter<'a> {
+    type Item = Report;
+    fn next(&mut self) -> Option<Report> {
+        let snapshots = &self.meter.snapshots;
+        if self.index >= snapshots.len() {
+            return None;
+        }
+        let report = self.meter.report_between(
+            &snapshots[self.index-1], &snapshots[self.index]);
+        self.index += 1;
+        Some(report)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.meter.snapshots.len().saturating_sub(self.index);
+        (left, Some(left))
     }
 }
 
+impl<'a> ExactSizeIterator for ReportHistoryIter<'a> {}
+
 impl<'a> Iterator for ThreadReportIter<'a> {
     type Item = (&'a str, ThreadReport);
     fn next(&mut self) -> Option<(&'a str, ThreadReport)> {
-        while let Some((&pid, name)) = self.threads.next() {
+        for (&pid, name) in self.threads.by_ref() {
             let lth = if let Some(thread) = self.last.threads.get(&pid) {
                 thread
             } else {
@@ -110,12 +623,27 @@ impl<'a> Iterator for ThreadReportIter<'a> {
             } else {
                 continue;  // not enough stats for a thread yet
             };
-            let udelta = lth.user_time - pth.user_time;
-            let sdelta = lth.system_time - pth.system_time;
+            let udelta = lth.user_time.saturating_sub(pth.user_time);
+            let sdelta = lth.system_time.saturating_sub(pth.system_time);
             return Some((&name[..], ThreadReport {
-                cpu_usage: 100.0 * (udelta + sdelta) as f32 / self.centisecs,
+                cpu_usage: 100.0 * udelta.saturating_add(sdelta) as f32
+                    / self.centisecs,
+                cpu_usage_with_children: 100.0 * gross_cpu_time(lth)
+                    .saturating_sub(gross_cpu_time(pth)) as f32
+                    / self.centisecs,
                 system_cpu: 100.0 * sdelta as f32 / self.centisecs,
                 user_cpu: 100.0 * udelta as f32 / self.centisecs,
+                last_cpu: lth.processor,
+                migrated: lth.processor != pth.processor,
+                io_read: 100.0 * lth.read_bytes
+                    .saturating_sub(pth.read_bytes) as f32 / self.centisecs,
+                io_write: 100.0 * lth.write_bytes
+                    .saturating_sub(pth.write_bytes) as f32 / self.centisecs,
+                // schedstat is in nanoseconds, a centisecond is 10^7 ns
+                run_time: lth.run_time_ns.saturating_sub(pth.run_time_ns)
+                    as f32 / (self.centisecs * 1e7),
+                wait_time: lth.wait_time_ns.saturating_sub(pth.wait_time_ns)
+                    as f32 / (self.centisecs * 1e7),
             }))
         }
         None
@@ -124,6 +652,346 @@ impl<'a> Iterator for ThreadReportIter<'a> {
         let (_min, max) = self.threads.size_hint();
         // unfortunately we skip non-scanned threads, so we must assume that
         // minimum size is zero
-        return (0, max);
+        (0, max)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    #[cfg(feature="serde")] use serde_json;
+
+    use {Meter, MeterBuilder, Snapshot, CpuSource, WarmupBehavior};
+
+    /// Returns empty snapshot taken at `uptime` (in centiseconds)
+    pub fn snapshot(uptime: u64, idle_time: u64) -> Snapshot {
+        let mut snap = Snapshot::new(&HashMap::new());
+        snap.uptime = uptime;
+        snap.idle_time = idle_time;
+        snap
+    }
+
+    /// Returns meter having `prev` and `last` as the two latest scans
+    pub fn meter_with(prev: Snapshot, last: Snapshot) -> Meter {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        meter.snapshots.push_back(prev);
+        meter.snapshots.push_back(last);
+        meter
+    }
+
+    #[test]
+    fn zero_cpus() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        meter.set_num_cpus(0);
+        assert_eq!(meter.num_cpus, 1);
+        // bypass the setter to check that report() is safe on its own
+        meter.num_cpus = 0;
+        meter.snapshots.push_back(snapshot(100, 50));
+        meter.snapshots.push_back(snapshot(200, 100));
+        let report = meter.report().unwrap();
+        assert!(report.global_cpu_usage.is_finite());
+        assert!(report.parallelism.is_finite());
+    }
+
+    #[test]
+    fn idle_decreased() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        let mut prev = snapshot(100, 50);
+        prev.stat_idle = 100;
+        prev.stat_total = 200;
+        let mut last = snapshot(200, 40);
+        last.stat_idle = 125;
+        last.stat_total = 300;
+        meter.snapshots.push_back(prev);
+        meter.snapshots.push_back(last);
+        let report = meter.report().unwrap();
+        assert_eq!(report.global_cpu_source, CpuSource::ProcStat);
+        assert_eq!(report.global_cpu_usage, 75.);
+    }
+
+    #[test]
+    fn warmup_zero_report() {
+        let mut meter = MeterBuilder::new(Duration::new(1, 0))
+            .warmup_behavior(WarmupBehavior::ZeroReport)
+            .build().unwrap();
+        assert!(meter.report().is_none());
+        let mut snap = snapshot(100, 50);
+        snap.memory_rss = 1024;
+        snap.read_bytes = 4096;
+        meter.snapshots.push_back(snap);
+        let report = meter.report().unwrap();
+        assert_eq!(report.memory_rss, 1024);
+        assert_eq!(report.global_cpu_usage, 0.);
+        assert_eq!(report.process_cpu_usage, 0.);
+        assert_eq!(report.io_read, 0.);
+    }
+
+    #[test]
+    fn usage_excluding() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        meter.track_thread(1, "main");
+        meter.track_thread(2, "gc");
+        let prev = Snapshot::new(&meter.thread_names);
+        let mut last = Snapshot::new(&meter.thread_names);
+        last.uptime = 100;
+        last.process.user_time = 75;
+        last.threads.get_mut(&1).unwrap().user_time = 50;
+        last.threads.get_mut(&2).unwrap().user_time = 25;
+        meter.snapshots.push_back(prev);
+        meter.snapshots.push_back(last);
+        assert_eq!(meter.cpu_usage_for(|name| name == "gc"), Some(25.));
+        assert_eq!(meter.cpu_usage_excluding(|name| name == "gc"), Some(50.));
+    }
+
+    #[test]
+    fn large_counters() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        let mut prev = snapshot(u64::MAX - 100, u64::MAX - 100);
+        prev.process.user_time = u64::MAX - 100;
+        prev.process.child_user_time = u64::MAX - 100;
+        let mut last = snapshot(u64::MAX, u64::MAX);
+        last.process.user_time = u64::MAX;
+        last.process.system_time = u64::MAX;
+        last.process.child_user_time = u64::MAX;
+        meter.snapshots.push_back(prev);
+        meter.snapshots.push_back(last);
+        let report = meter.report().unwrap();
+        assert!(report.process_cpu_usage.is_finite());
+        assert!(report.gross_cpu_usage.is_finite());
+        assert!(report.system_uptime > Duration::new(1 << 40, 0));
+    }
+
+    #[test]
+    fn counter_reset() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        meter.track_thread(1, "main");
+        let mut prev = Snapshot::new(&meter.thread_names);
+        prev.uptime = 100;
+        prev.read_bytes = 1 << 20;
+        prev.write_disk_bytes = 1 << 20;
+        prev.process.user_time = 50;
+        prev.threads.get_mut(&1).unwrap().user_time = 50;
+        let mut last = Snapshot::new(&meter.thread_names);
+        last.uptime = 200;
+        last.read_bytes = 1024;
+        meter.snapshots.push_back(prev);
+        meter.snapshots.push_back(last);
+        let report = meter.report().unwrap();
+        assert_eq!(report.io_read, 0.);
+        assert_eq!(report.disk_write, 0.);
+        assert_eq!(report.process_cpu_usage, 0.);
+        for (_, thread) in meter.thread_report().unwrap() {
+            assert_eq!(thread.cpu_usage, 0.);
+        }
+    }
+
+    #[test]
+    fn same_uptime() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        meter.track_thread(1, "main");
+        let prev = Snapshot::new(&meter.thread_names);
+        let mut last = Snapshot::new(&meter.thread_names);
+        last.instant = prev.instant + Duration::from_millis(5);
+        last.process.user_time = 1;
+        last.threads.get_mut(&1).unwrap().user_time = 1;
+        last.read_bytes = 100;
+        meter.snapshots.push_back(prev);
+        meter.snapshots.push_back(last);
+        let report = meter.report().unwrap();
+        assert!(report.process_cpu_usage.is_finite());
+        assert!(report.global_cpu_usage.is_finite());
+        assert!(report.io_read.is_finite());
+        assert!(report.io_read > 0.);
+        for (_, thread) in meter.thread_report().unwrap() {
+            assert!(thread.cpu_usage.is_finite());
+        }
+    }
+
+    #[test]
+    #[cfg(feature="serde")]
+    fn json_round_trip() {
+        use std::time::UNIX_EPOCH;
+
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        let mut prev = snapshot(100, 50);
+        prev.process.user_time = 10;
+        prev.read_bytes = 1000;
+        let mut last = snapshot(200, 100);
+        last.process.user_time = 35;
+        last.read_bytes = 5000;
+        last.memory_rss = 1 << 20;
+        meter.snapshots.push_back(prev);
+        meter.snapshots.push_back(last);
+        let mut report = meter.report().unwrap();
+        // timestamps are serialized with millisecond precision
+        let ms = |d: Duration| Duration::from_millis(d.as_millis() as u64);
+        report.timestamp = UNIX_EPOCH +
+            ms(report.timestamp.duration_since(UNIX_EPOCH).unwrap());
+        report.start_time = UNIX_EPOCH +
+            ms(report.start_time.duration_since(UNIX_EPOCH).unwrap());
+        report.duration = ms(report.duration);
+        let json = serde_json::to_string(&report).unwrap();
+        let decoded = serde_json::from_str(&json).unwrap();
+        assert_eq!(report, decoded);
+    }
+
+    #[test]
+    fn report_over() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        assert!(meter.report_over(Duration::new(10, 0)).is_none());
+        let mut first = snapshot(100, 50);
+        let mut second = snapshot(200, 100);
+        let mut third = snapshot(300, 150);
+        second.instant = first.instant + Duration::new(1, 0);
+        third.instant = first.instant + Duration::new(2, 0);
+        first.process.user_time = 0;
+        second.process.user_time = 100;
+        third.process.user_time = 100;
+        meter.snapshots.push_back(first);
+        meter.snapshots.push_back(second);
+        meter.snapshots.push_back(third);
+        let wide = meter.report_over(Duration::new(10, 0)).unwrap();
+        assert_eq!(wide.process_cpu_usage, 50.);
+        let narrow = meter.report_over(Duration::new(1, 0)).unwrap();
+        assert_eq!(narrow.process_cpu_usage, 0.);
+        assert!(meter.report_over(Duration::from_millis(500)).is_none());
+    }
+
+    #[test]
+    fn thread_report_sorted() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        meter.track_thread(1, "idle");
+        meter.track_thread(2, "worker");
+        meter.track_thread(3, "another");
+        let mut prev = Snapshot::new(&meter.thread_names);
+        prev.uptime = 100;
+        let mut last = Snapshot::new(&meter.thread_names);
+        last.uptime = 200;
+        last.threads.get_mut(&2).unwrap().user_time = 50;
+        meter.snapshots.push_back(prev);
+        meter.snapshots.push_back(last);
+        let names = meter.thread_report_sorted().unwrap().into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["worker", "another", "idle"]);
+    }
+
+    #[test]
+    fn thrashing() {
+        let thrashing = |faults: u64, swap_in: u64| {
+            let mut last = snapshot(200, 0);
+            last.process.major_faults = faults;
+            last.swap_in_pages = swap_in;
+            meter_with(snapshot(100, 0), last).report().unwrap()
+                .thrashing_indicator
+        };
+        // idle
+        assert_eq!(thrashing(0, 0), 0.);
+        // faulting from disk while swap is idle
+        assert_eq!(thrashing(100, 0), 0.75);
+        assert_eq!(thrashing(40, 0), 0.3);
+        // other processes swapping
+        assert_eq!(thrashing(0, 1000), 0.25);
+        // faulting from swap
+        assert_eq!(thrashing(100, 100), 1.);
+        assert_eq!(thrashing(1000, 1000), 1.);
+    }
+
+    #[test]
+    fn child_cpu_time() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        assert_eq!(meter.child_cpu_time(), None);
+        let mut snap = snapshot(100, 50);
+        snap.process.user_time = 1000;
+        snap.process.child_user_time = 150;
+        snap.process.child_system_time = 25;
+        meter.snapshots.push_back(snap);
+        assert_eq!(meter.child_cpu_time(), Some(Duration::from_millis(1750)));
+        assert_eq!(meter.total_cpu_time(), Some(Duration::new(10, 0)));
+    }
+
+    #[test]
+    fn child_cpu_fraction() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        let prev = snapshot(100, 50);
+        let mut last = snapshot(200, 100);
+        last.process.user_time = 25;
+        last.process.child_user_time = 25;
+        meter.snapshots.push_back(prev);
+        meter.snapshots.push_back(last);
+        let mut report = meter.report().unwrap();
+        assert_eq!(report.child_cpu_fraction(), 0.5);
+        report.gross_cpu_usage = 0.;
+        assert_eq!(report.child_cpu_fraction(), 0.);
+        report.gross_cpu_usage = report.process_cpu_usage / 2.;
+        assert_eq!(report.child_cpu_fraction(), 0.);
+    }
+
+    #[test]
+    fn memory_pressure() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        meter.snapshots.push_back(snapshot(100, 50));
+        meter.snapshots.push_back(snapshot(200, 100));
+        let mut report = meter.report().unwrap();
+        report.memory_limit = None;
+        assert_eq!(report.memory_pressure_score(), None);
+        report.memory_limit = Some(1000);
+        report.memory_rss = 500;
+        report.memory_swap = 2000;
+        report.major_faults = 50.;
+        let pressure = report.memory_pressure().unwrap();
+        assert_eq!(pressure.rss, 0.5);
+        assert_eq!(pressure.swap, 1.);
+        assert_eq!(pressure.major_faults, 0.5);
+        assert_eq!(report.memory_pressure_score(), Some(0.625));
+    }
+
+    #[test]
+    fn full_report() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        meter.track_thread(1, "worker");
+        assert!(meter.full_report().is_none());
+        let mut prev = Snapshot::new(&meter.thread_names);
+        prev.uptime = 100;
+        let mut last = Snapshot::new(&meter.thread_names);
+        last.uptime = 200;
+        last.process.user_time = 50;
+        last.threads.get_mut(&1).unwrap().user_time = 50;
+        meter.snapshots.push_back(prev);
+        meter.snapshots.push_back(last);
+        let (report, threads) = meter.full_report().unwrap();
+        assert_eq!(report, meter.report().unwrap());
+        assert_eq!(threads, meter.thread_report_sorted().unwrap());
+        assert_eq!(threads[0].1.cpu_usage, report.process_cpu_usage);
+    }
+
+    #[test]
+    fn suspended() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        let prev = snapshot(100, 50);
+        let mut last = snapshot(200, 100);
+        let start = prev.timestamp;
+        last.timestamp = start + Duration::new(1, 0);
+        meter.snapshots.push_back(prev);
+        meter.snapshots.push_back(last);
+        assert!(!meter.report().unwrap().suspended_during_interval);
+        meter.snapshots[1].timestamp = start + Duration::new(60, 0);
+        assert!(meter.report().unwrap().suspended_during_interval);
+    }
+
+    #[test]
+    fn history() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        assert_eq!(meter.report_slice().len(), 0);
+        meter.snapshots.push_back(snapshot(100, 0));
+        assert_eq!(meter.report_history().len(), 0);
+        meter.snapshots.push_back(snapshot(200, 0));
+        meter.snapshots.push_back(snapshot(400, 0));
+        let history = meter.report_slice();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].system_uptime, Duration::new(2, 0));
+        assert_eq!(history[1].system_uptime, Duration::new(4, 0));
     }
 }
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use {Meter, Report, Error};
+
+
+/// Validator set by `Meter::set_sample_validator`
+pub type SampleValidator = Box<dyn FnMut(&Report) -> bool + Send>;
+
+/// Result of `Meter::scan_checked`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanStatus {
+    /// Sample looks fine, scan again after the regular interval
+    Normal,
+    /// Last interval is suspect, scan again soon to get a fresh one
+    RescanSoon,
+}
+
+impl Meter {
+    /// Set a closure that checks whether a report looks plausible
+    ///
+    /// The validator receives the report of the last interval and returns
+    /// `false` if the sample is anomalous. It may capture thresholds or
+    /// keep state between the calls. It's called by `scan_checked`
+    /// in addition to the built-in check of the interval length.
+    pub fn set_sample_validator(&mut self, validator: SampleValidator) {
+        self.sample_validator = Some(validator);
+    }
+    /// Scan and check whether the last interval can be trusted
+    ///
+    /// Works like `scan()`, but returns `ScanStatus::RescanSoon` when the
+    /// interval was more than twice as long as `scan_interval` (i.e. the
+    /// process was stalled or system was sleeping) or when the validator
+    /// set by `set_sample_validator` rejects the report. In this case all
+    /// the history but the latest snapshot is discarded, so the next scan
+    /// yields a fresh report. Use `next_scan_delay()` to know when to scan.
+    pub fn scan_checked(&mut self) -> Result<ScanStatus, Error> {
+        self.scan()?;
+        let report = if self.snapshots.len() >= 2 {
+            self.report()
+        } else {
+            None
+        };
+        let suspect = match report {
+            Some(ref report) => {
+                report.duration > self.scan_interval * 2 ||
+                    self.sample_validator.as_mut()
+                        .map(|f| !f(report)).unwrap_or(false)
+            }
+            None => false,
+        };
+        self.rescan_requested = suspect;
+        if suspect {
+            let keep = self.snapshots.len() - 1;
+            self.snapshots.drain(..keep);
+            Ok(ScanStatus::RescanSoon)
+        } else {
+            Ok(ScanStatus::Normal)
+        }
+    }
+    /// Returns how long to sleep before the next `scan_checked()`
+    ///
+    /// This is `scan_interval`, or a tenth of it if the last scan
+    /// requested a rescan.
+    pub fn next_scan_delay(&self) -> Duration {
+        if self.rescan_requested {
+            self.scan_interval / 10
+        } else {
+            self.scan_interval
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use Meter;
+    use super::ScanStatus;
+
+    #[test]
+    fn validator() {
+        let interval = Duration::new(1, 0);
+        let mut meter = Meter::new(interval).unwrap();
+        let max_rss = 0;
+        meter.set_sample_validator(Box::new(move |report| {
+            report.memory_rss <= max_rss
+        }));
+        assert_eq!(meter.scan_checked().unwrap(), ScanStatus::Normal);
+        assert_eq!(meter.next_scan_delay(), interval);
+        assert_eq!(meter.scan_checked().unwrap(), ScanStatus::RescanSoon);
+        assert_eq!(meter.next_scan_delay(), interval / 10);
+        assert_eq!(meter.snapshots.len(), 1);
+
+        meter.set_sample_validator(Box::new(|_| true));
+        assert_eq!(meter.scan_checked().unwrap(), ScanStatus::Normal);
+        assert_eq!(meter.next_scan_delay(), interval);
+        assert_eq!(meter.snapshots.len(), 2);
+    }
+}
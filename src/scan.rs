@@ -1,20 +1,100 @@
-use std::io::{Read, Seek, SeekFrom};
-use std::fs::File;
-use std::fmt::Write;
-use std::num::ParseIntError;
+use std::io;
+#[cfg(target_os="linux")] use std::io::{Read, BufRead, BufReader};
+#[cfg(target_os="linux")] use std::fs::{self, File};
+#[cfg(target_os="linux")] use std::fmt::Write;
+#[cfg(target_os="linux")] use std::path::Path;
 use std::time::{Instant, SystemTime};
+use std::mem;
 use std::collections::HashMap;
 
-use {Meter, Snapshot, ThreadInfo, Pid, Error};
-use error::{UptimeError, StatError, StatusError, IoStatError};
+use {Meter, Snapshot, ThreadInfo, Pid, Error, IoBaseline};
+use {Report, ThreadReportIter};
+#[cfg(target_os="linux")] use {RegionKind, FdKind, CoreTimes};
+use error::{StatError, StatusError, VmStatError};
+#[cfg(any(target_os="linux", feature="io-stats"))] use error::IoStatError;
+use error::NetDevError;
+#[cfg(target_os="linux")] use error::UptimeError;
+#[cfg(target_os="linux")] use threads::is_thread_gone;
+#[cfg(target_os="macos")] use macos;
 
 
 impl Meter {
     /// Scan system for metrics
     ///
     /// This method must be called regularly at intervals specified
-    /// in constructor.
+    /// in constructor. Does nothing if meter is paused with `pause()`.
+    /// With the `log` feature a warning is logged when the scan is more
+    /// than 1.5 intervals late.
     pub fn scan(&mut self) -> Result<(), Error> {
+        if self.paused {
+            return Ok(());
+        }
+        let started = Instant::now();
+        let result = self.scan_self()
+            .and_then(|()| self.scan_processes());
+        self.scan_time += started.elapsed();
+        result
+    }
+
+    /// Scan system for metrics and return report of the last interval
+    ///
+    /// This is a shortcut for `scan()` followed by `report()`. Returns
+    /// `Ok(None)` after the first scan, as two scans are needed.
+    ///
+    /// Callbacks registered by `on_threshold()` are checked against the
+    /// fresh report.
+    pub fn scan_and_report(&mut self) -> Result<Option<Report>, Error> {
+        self.scan()?;
+        let report = self.report();
+        if let Some(ref report) = report {
+            self.check_thresholds(report);
+        }
+        Ok(report)
+    }
+
+    /// Scan system for metrics and pass reports of the last interval to `f`
+    ///
+    /// The callback receives the process report and the iterator over
+    /// reports of tracked threads, both computed from the same pair of
+    /// snapshots. It's not called after the first scan, as two scans are
+    /// needed, and at most once per window set by `set_report_throttle()`.
+    /// Callbacks registered by `on_threshold()` are checked on every scan
+    /// as in `scan_and_report()`.
+    pub fn scan_and_notify<F>(&mut self, mut f: F) -> Result<(), Error>
+        where F: FnMut(&Report, ThreadReportIter)
+    {
+        self.scan()?;
+        if self.snapshots.len() < 2 {
+            return Ok(());
+        }
+        let report = match self.report() {
+            Some(report) => report,
+            None => return Ok(()),
+        };
+        if self.take_emit_slot() {
+            if let Some(threads) = self.thread_report() {
+                f(&report, threads);
+            }
+        }
+        self.check_thresholds(&report);
+        Ok(())
+    }
+
+    /// Scan system for metrics, tolerating errors of individual threads
+    ///
+    /// Works like `scan()`, but failure to read stats of some tracked
+    /// thread doesn't abort the scan. Such threads are skipped in the
+    /// next `thread_report()` and their errors are returned, while
+    /// process-level data is recorded as usual. Other errors are returned
+    /// as `Err` just like in `scan()`.
+    pub fn scan_lenient(&mut self) -> Result<Vec<(Pid, StatError)>, Error> {
+        self.thread_errors = Some(Vec::new());
+        let result = self.scan();
+        let errors = self.thread_errors.take().unwrap_or_default();
+        result.map(|()| errors)
+    }
+
+    fn scan_self(&mut self) -> Result<(), Error> {
         // We reuse Snapshot structure (mostly becasuse of threads hash map)
         // to have smaller allocations on the fast path
         let mut snap = if self.snapshots.len() >= self.num_snapshots {
@@ -22,62 +102,215 @@ impl Meter {
         } else {
             Snapshot::new(&self.thread_names)
         };
-        snap.timestamp = SystemTime::now();
-        snap.instant = Instant::now();
+        self.take_snapshot(&mut snap)?;
 
-        // First scan everything that relates to cpu_time to have as accurate
-        // CPU usage measurements as possible
-        try!(self.read_cpu_times(&mut snap.process,
-            &mut snap.threads,
-            &mut snap.uptime, &mut snap.idle_time));
+        if self.io_baseline_pending {
+            self.io_baseline = IoBaseline::from_snapshot(&snap);
+            self.io_baseline_pending = false;
+        }
+
+        self.update_peaks(&snap);
+
+        if let Some(prev) = self.snapshots.back() {
+            let interval = snap.instant.duration_since(prev.instant);
+            let deviation = interval.abs_diff(self.scan_interval);
+            #[cfg(feature="log")]
+            if interval > self.scan_interval * 3 / 2 {
+                warn!("self-meter: scan is late, {:?} since previous scan \
+                       while scan interval is {:?}",
+                       interval, self.scan_interval);
+            }
+            self.jitter_samples = self.jitter_samples.saturating_add(1);
+            self.jitter_total = self.jitter_total.saturating_add(deviation);
+            if deviation > self.jitter_max {
+                self.jitter_max = deviation;
+            }
+        }
 
-        try!(self.read_memory(&mut snap));
-        try!(self.read_io(&mut snap));
+        self.snapshots.push_back(snap);
+        Ok(())
+    }
 
+    /// Updates peak values with the new snapshot (not yet in history)
+    pub(crate) fn update_peaks(&mut self, snap: &Snapshot) {
         if snap.memory_rss > self.memory_rss_peak {
             self.memory_rss_peak = snap.memory_rss;
         }
         if snap.memory_swap > self.memory_swap_peak {
             self.memory_swap_peak = snap.memory_swap;
         }
+        if snap.num_threads > self.num_threads_peak {
+            self.num_threads_peak = snap.num_threads;
+        }
+        if snap.fd_count > self.fd_count_peak {
+            self.fd_count_peak = snap.fd_count;
+        }
+        if let Some(prev) = self.snapshots.back() {
+            let cpu_usage = self.process_cpu_between(prev, snap);
+            if cpu_usage > self.process_cpu_peak {
+                self.process_cpu_peak = cpu_usage;
+            }
+        }
+    }
 
-        self.snapshots.push_back(snap);
+    /// Fills in the snapshot with current values
+    ///
+    /// Doesn't touch the history, peaks, and tracked processes
+    pub(crate) fn take_snapshot(&mut self, snap: &mut Snapshot)
+        -> Result<(), Error>
+    {
+        snap.timestamp = SystemTime::now();
+        snap.instant = Instant::now();
+
+        // First scan everything that relates to cpu_time to have as accurate
+        // CPU usage measurements as possible
+        self.read_cpu_times(&mut snap.process,
+            &mut snap.threads,
+            &mut snap.uptime, &mut snap.idle_time)?;
+        self.read_proc_stat(snap).map_err(Error::ProcStat)?;
+
+        self.read_memory(snap)?;
+        #[cfg(feature="io-stats")]
+        self.read_io(snap)?;
+        if self.thread_io {
+            self.read_thread_io(snap)?;
+        }
+        if self.thread_schedstat {
+            self.read_thread_schedstat(snap);
+        }
+        self.read_net_dev(snap)?;
+        self.read_vmstat(snap)?;
+        self.read_meminfo(snap).map_err(Error::MemInfo)?;
+        self.read_fd_count(snap).map_err(Error::FdCount)?;
+        if self.memory_regions {
+            self.read_smaps(snap).map_err(Error::Smaps)?;
+        }
+        if self.numa_maps {
+            self.read_numa_maps(snap).map_err(Error::NumaMaps)?;
+        }
+        if self.fd_types {
+            self.read_fd_types(snap).map_err(Error::FdTypes)?;
+        }
         Ok(())
     }
 
+    fn scan_processes(&mut self) -> Result<(), Error> {
+        let mut result = Ok(());
+        for (&pid, meter) in &mut self.processes {
+            mem::swap(&mut self.text_buf, &mut meter.text_buf);
+            mem::swap(&mut self.path_buf, &mut meter.path_buf);
+            let res = meter.scan();
+            mem::swap(&mut self.text_buf, &mut meter.text_buf);
+            mem::swap(&mut self.path_buf, &mut meter.path_buf);
+            if let Err(e) = res {
+                if result.is_ok() {
+                    result = Err(Error::Process(pid, Box::new(e)));
+                }
+            }
+        }
+        result
+    }
+
     #[cfg(target_os="linux")]
     fn read_cpu_times(&mut self, process: &mut ThreadInfo,
                       threads: &mut HashMap<Pid, ThreadInfo>,
                       uptime: &mut u64, idle_time: &mut u64)
         -> Result<(), Error>
     {
-        self.text_buf.truncate(0);
-        try!(File::open("/proc/uptime")
-             .and_then(|mut f| f.read_to_string(&mut self.text_buf))
-             .map_err(|e| Error::Uptime(e.into())));
+        read_open_file(&mut self.uptime_file, &mut self.text_buf)
+            .map_err(|e| Error::Uptime(e.into()))?;
         {
             let mut iter = self.text_buf.split_whitespace();
-            let seconds = try!(iter.next()
-                .ok_or(Error::Uptime(UptimeError::BadFormat)));
-            let idle_sec = try!(iter.next()
-                .ok_or(Error::Uptime(UptimeError::BadFormat)));
-            *uptime = try!(parse_uptime(seconds));
-            *idle_time = try!(parse_uptime(idle_sec));
-        }
-        try!(read_stat(&mut self.text_buf, "/proc/self/stat", process)
-            .map_err(Error::Stat));
-        for (&tid, _) in &self.thread_names {
-            self.path_buf.truncate(0);
-            write!(&mut self.path_buf,
-                "/proc/self/task/{}/stat", tid).unwrap();
-            try!(read_stat(&mut self.text_buf, &self.path_buf[..],
-                    threads.entry(tid).or_insert_with(ThreadInfo::new))
-                .map_err(|e| Error::ThreadStat(tid, e)));
+            let seconds = iter.next()
+                .ok_or(Error::Uptime(UptimeError::BadFormat))?;
+            let idle_sec = iter.next()
+                .ok_or(Error::Uptime(UptimeError::BadFormat))?;
+            *uptime = parse_uptime(seconds)?;
+            *idle_time = parse_uptime(idle_sec)?;
+        }
+        read_open_file(&mut self.stat_file, &mut self.text_buf)
+            .map_err(|e| Error::Stat(e.into()))?;
+        parse_stat(&self.text_buf, process).map_err(Error::Stat)?;
+        let mut exited = Vec::new();
+        for &tid in self.thread_names.keys() {
+            let cached = match self.thread_stat_files.get_mut(&tid) {
+                Some(file) => read_open_file(file, &mut self.text_buf).is_ok(),
+                None => false,
+            };
+            let result = if cached {
+                Ok(())
+            } else {
+                // first scan of the thread, or cached file is stale
+                self.thread_stat_files.remove(&tid);
+                proc_path(&mut self.path_buf, &self.proc_root, self.pid,
+                          "task");
+                write!(&mut self.path_buf, "/{}/stat", tid).unwrap();
+                match File::open(&self.path_buf) {
+                    Ok(mut file) => {
+                        let result = read_open_file(&mut file,
+                                                    &mut self.text_buf);
+                        self.thread_stat_files.insert(tid, file);
+                        result.map_err(StatError::from)
+                    }
+                    Err(e) => Err(StatError::from(e)),
+                }
+            };
+            let result = result.and_then(|()| parse_stat(&self.text_buf,
+                threads.entry(tid).or_insert_with(ThreadInfo::new)));
+            match result {
+                Ok(()) => {}
+                Err(StatError::Io(ref e)) if is_thread_gone(e) => {
+                    exited.push(tid);
+                }
+                Err(e) => match self.thread_errors {
+                    Some(ref mut errors) => {
+                        // stale values would give bogus report
+                        threads.remove(&tid);
+                        errors.push((tid, e));
+                    }
+                    None => return Err(Error::ThreadStat(tid, e)),
+                },
+            }
+        }
+        // dead threads don't break the scan, they are untracked instead
+        for tid in exited {
+            threads.remove(&tid);
+            self.untrack_thread(tid);
         }
         Ok(())
     }
 
-    #[cfg(not(target_os="linux"))]
+    #[cfg(target_os="macos")]
+    fn read_cpu_times(&mut self, process: &mut ThreadInfo,
+                      _threads: &mut HashMap<Pid, ThreadInfo>,
+                      uptime: &mut u64, idle_time: &mut u64)
+        -> Result<(), Error>
+    {
+        use libc::CPU_STATE_IDLE;
+
+        let ticks = macos::host_cpu_ticks()
+            .map_err(|e| Error::Uptime(e.into()))?;
+        *uptime = macos::uptime();
+        *idle_time = ticks.cpu_ticks[CPU_STATE_IDLE as usize] as u64;
+        let usage = macos::rusage(self.pid)
+            .map_err(|e| Error::Stat(e.into()))?;
+        process.user_time = macos::abs_to_centisecs(usage.ri_user_time);
+        process.system_time = macos::abs_to_centisecs(usage.ri_system_time);
+        process.child_user_time =
+            macos::abs_to_centisecs(usage.ri_child_user_time);
+        process.child_system_time =
+            macos::abs_to_centisecs(usage.ri_child_system_time);
+        process.start_time =
+            macos::abs_to_centisecs(usage.ri_proc_start_abstime);
+        let info = macos::task_info(self.pid)
+            .map_err(|e| Error::Stat(e.into()))?;
+        process.minor_faults = info.pti_faults as u64;
+        process.major_faults = info.pti_pageins as u64;
+        // thread breakdown isn't supported on macOS yet
+        Ok(())
+    }
+
+    #[cfg(not(any(target_os="linux", target_os="macos")))]
     fn read_cpu_times(&mut self, process: &mut ThreadInfo,
                       threads: &mut HashMap<Pid, ThreadInfo>,
                       uptime: &mut u64, idle_time: &mut u64)
@@ -86,46 +319,148 @@ impl Meter {
         Ok(())
     }
 
+    #[cfg(target_os="linux")]
+    fn read_proc_stat(&mut self, snap: &mut Snapshot)
+        -> Result<(), StatError>
+    {
+        snap.stat_idle = 0;
+        snap.stat_total = 0;
+        snap.stat_iowait = 0;
+        snap.stat_steal = 0;
+        snap.stat_guest = 0;
+        snap.cores.truncate(0);
+        proc_file(&mut self.path_buf, &self.proc_root, "stat");
+        // may be hidden in sandboxes, report falls back to /proc/uptime
+        if read_file(&self.path_buf, &mut self.text_buf).is_err() {
+            return Ok(());
+        }
+        let line = self.text_buf.lines().next()
+            .ok_or(StatError::BadFormat)?;
+        let mut iter = line.split_whitespace();
+        if iter.next() != Some("cpu") {
+            return Err(StatError::BadFormat);
+        }
+        // user nice system idle iowait irq softirq steal guest guest_nice,
+        // older kernels omit the last ones
+        for (idx, value) in iter.take(10).enumerate() {
+            let value: u64 = value.parse()?;
+            if idx >= 8 {
+                // guest time is already accounted in user and nice
+                snap.stat_guest = snap.stat_guest.saturating_add(value);
+                continue;
+            }
+            if idx == 3 || idx == 4 {
+                snap.stat_idle = snap.stat_idle.saturating_add(value);
+            }
+            if idx == 4 {
+                snap.stat_iowait = value;
+            }
+            if idx == 7 {
+                snap.stat_steal = value;
+            }
+            snap.stat_total = snap.stat_total.saturating_add(value);
+        }
+        for line in self.text_buf.lines().skip(1) {
+            if !line.starts_with("cpu") {
+                // per-core lines go right after the aggregate one
+                break;
+            }
+            snap.cores.push(parse_core_line(line)?);
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os="macos")]
+    fn read_proc_stat(&mut self, snap: &mut Snapshot)
+        -> Result<(), StatError>
+    {
+        use libc::CPU_STATE_IDLE;
+
+        let ticks = macos::host_cpu_ticks()?;
+        snap.stat_idle = ticks.cpu_ticks[CPU_STATE_IDLE as usize] as u64;
+        snap.stat_total = ticks.cpu_ticks.iter().map(|&x| x as u64).sum();
+        Ok(())
+    }
+
+    #[cfg(not(any(target_os="linux", target_os="macos")))]
+    fn read_proc_stat(&mut self, snap: &mut Snapshot)
+        -> Result<(), StatError>
+    {
+        Ok(())
+    }
+
     #[cfg(target_os="linux")]
     fn read_memory(&mut self, snap: &mut Snapshot)
         -> Result<(), StatusError>
     {
-        self.text_buf.truncate(0);
-        try!(File::open("/proc/self/status")
-             .and_then(|mut f| f.read_to_string(&mut self.text_buf)));
+        read_open_file(&mut self.status_file, &mut self.text_buf)?;
         for line in self.text_buf.lines() {
             let mut pairs = line.split(':');
             match (pairs.next(), pairs.next()) {
                 (Some("VmPeak"), Some(text))
-                => snap.memory_virtual_peak = try!(parse_memory(text)),
+                => snap.memory_virtual_peak = parse_memory(text)?,
                 (Some("VmSize"), Some(text))
-                => snap.memory_virtual = try!(parse_memory(text)),
+                => snap.memory_virtual = parse_memory(text)?,
+                (Some("VmHWM"), Some(text))
+                => snap.memory_rss_hwm = parse_memory(text)?,
                 (Some("VmRSS"), Some(text))
-                => snap.memory_rss = try!(parse_memory(text)),
+                => snap.memory_rss = parse_memory(text)?,
                 (Some("VmSwap"), Some(text))
-                => snap.memory_swap = try!(parse_memory(text)),
+                => snap.memory_swap = parse_memory(text)?,
+                (Some("RssAnon"), Some(text))
+                => snap.memory_rss_anon = parse_memory(text)?,
+                (Some("RssFile"), Some(text))
+                => snap.memory_rss_file = parse_memory(text)?,
+                (Some("RssShmem"), Some(text))
+                => snap.memory_rss_shmem = parse_memory(text)?,
+                (Some("VmData"), Some(text))
+                => snap.memory_data = parse_memory(text)?,
+                (Some("VmStk"), Some(text))
+                => snap.memory_stack = parse_memory(text)?,
+                (Some("VmExe"), Some(text))
+                => snap.memory_exe = parse_memory(text)?,
+                (Some("VmLib"), Some(text))
+                => snap.memory_lib = parse_memory(text)?,
+                (Some("Threads"), Some(text))
+                => snap.num_threads = text.trim().parse()?,
+                (Some("voluntary_ctxt_switches"), Some(text))
+                => snap.voluntary_ctxt_switches = text.trim().parse()?,
+                (Some("nonvoluntary_ctxt_switches"), Some(text))
+                => snap.nonvoluntary_ctxt_switches = text.trim().parse()?,
+                (Some("TracerPid"), Some(text))
+                => snap.tracer_pid = text.trim().parse()?,
                 _ => {}
             }
         }
         Ok(())
     }
 
-    #[cfg(not(target_os="linux"))]
+    #[cfg(target_os="macos")]
     fn read_memory(&mut self, snap: &mut Snapshot)
         -> Result<(), StatusError>
     {
+        let info = macos::task_info(self.pid)?;
+        snap.memory_rss = info.pti_resident_size;
+        snap.memory_virtual = info.pti_virtual_size;
+        snap.num_threads = info.pti_threadnum as u32;
         Ok(())
     }
 
-    #[cfg(target_os="linux")]
+    #[cfg(not(any(target_os="linux", target_os="macos")))]
+    fn read_memory(&mut self, snap: &mut Snapshot)
+        -> Result<(), StatusError>
+    {
+        Ok(())
+    }
+
+    #[cfg(all(target_os="linux", feature="io-stats"))]
     fn read_io(&mut self, snap: &mut Snapshot)
         -> Result<(), Error>
     {
+        use std::num::ParseIntError;
+
         let err = &|e: ParseIntError| Error::IoStat(e.into());
-        self.text_buf.truncate(0);
-        self.io_file.seek(SeekFrom::Start(0))
-            .map_err(IoStatError::Io)?;
-        self.io_file.read_to_string(&mut self.text_buf)
+        read_open_file(&mut self.io_file, &mut self.text_buf)
             .map_err(IoStatError::Io)?;
         for line in self.text_buf.lines() {
             let mut pairs = line.split(':');
@@ -150,7 +485,18 @@ impl Meter {
         }
         Ok(())
     }
-    #[cfg(not(target_os="linux"))]
+    #[cfg(all(target_os="macos", feature="io-stats"))]
+    fn read_io(&mut self, snap: &mut Snapshot)
+        -> Result<(), Error>
+    {
+        // only block IO is accounted by the kernel
+        let usage = macos::rusage(self.pid).map_err(IoStatError::Io)?;
+        snap.read_disk_bytes = usage.ri_diskio_bytesread;
+        snap.write_disk_bytes = usage.ri_diskio_byteswritten;
+        Ok(())
+    }
+    #[cfg(all(not(any(target_os="linux", target_os="macos")),
+              feature="io-stats"))]
     fn read_io(&mut self, snap: &mut Snapshot)
         -> Result<(), Error>
     {
@@ -158,18 +504,415 @@ impl Meter {
         Ok(())
     }
 
+    #[cfg(target_os="linux")]
+    fn read_thread_io(&mut self, snap: &mut Snapshot) -> Result<(), Error> {
+        for &tid in self.thread_names.keys() {
+            proc_path(&mut self.path_buf, &self.proc_root, self.pid, "task");
+            write!(&mut self.path_buf, "/{}/io", tid).unwrap();
+            let thread = snap.threads.entry(tid)
+                .or_insert_with(ThreadInfo::new);
+            read_thread_io(&mut self.text_buf, &self.path_buf, thread)
+                .map_err(|e| Error::ThreadIoStat(tid, e))?;
+        }
+        Ok(())
+    }
+    #[cfg(not(target_os="linux"))]
+    fn read_thread_io(&mut self, _snap: &mut Snapshot) -> Result<(), Error> {
+        Ok(())
+    }
+
+    #[cfg(target_os="linux")]
+    fn read_thread_schedstat(&mut self, snap: &mut Snapshot) {
+        for &tid in self.thread_names.keys() {
+            proc_path(&mut self.path_buf, &self.proc_root, self.pid, "task");
+            write!(&mut self.path_buf, "/{}/schedstat", tid).unwrap();
+            // file is absent if kernel has schedstats disabled
+            let (run, wait) = read_file(&self.path_buf, &mut self.text_buf)
+                .ok()
+                .and_then(|()| parse_schedstat(&self.text_buf))
+                .unwrap_or((0, 0));
+            let thread = snap.threads.entry(tid)
+                .or_insert_with(ThreadInfo::new);
+            thread.run_time_ns = run;
+            thread.wait_time_ns = wait;
+        }
+    }
+    #[cfg(not(target_os="linux"))]
+    fn read_thread_schedstat(&mut self, _snap: &mut Snapshot) {
+    }
+
+    #[cfg(target_os="linux")]
+    fn read_smaps(&mut self, snap: &mut Snapshot)
+        -> Result<(), StatusError>
+    {
+        snap.memory_by_region.clear();
+        proc_path(&mut self.path_buf, &self.proc_root, self.pid, "smaps");
+        // smaps may be huge, so we read it line by line
+        let mut file = BufReader::new(File::open(&self.path_buf)?);
+        let mut kind = None;
+        loop {
+            self.text_buf.truncate(0);
+            if file.read_line(&mut self.text_buf)? == 0 {
+                break;
+            }
+            let line = &self.text_buf[..];
+            match line.find(':') {
+                // field names have no spaces, header has device `08:01`
+                Some(colon) if !line[..colon].contains(' ') => {
+                    if &line[..colon] == "Rss" {
+                        let rss = parse_memory(&line[colon+1..])?;
+                        if let Some(kind) = kind {
+                            *snap.memory_by_region.entry(kind).or_insert(0)
+                                += rss;
+                        }
+                    }
+                }
+                _ => kind = parse_mapping_kind(line),
+            }
+        }
+        Ok(())
+    }
+    #[cfg(not(target_os="linux"))]
+    fn read_smaps(&mut self, _snap: &mut Snapshot)
+        -> Result<(), StatusError>
+    {
+        Ok(())
+    }
+
+    #[cfg(target_os="linux")]
+    fn read_numa_maps(&mut self, snap: &mut Snapshot)
+        -> Result<(), StatusError>
+    {
+        use libc::{sysconf, _SC_PAGESIZE};
+        let page_size = unsafe { sysconf(_SC_PAGESIZE) } as u64;
+        snap.memory_by_numa_node.clear();
+        proc_path(&mut self.path_buf, &self.proc_root, self.pid, "numa_maps");
+        let mut file = BufReader::new(File::open(&self.path_buf)?);
+        loop {
+            self.text_buf.truncate(0);
+            if file.read_line(&mut self.text_buf)? == 0 {
+                break;
+            }
+            parse_numa_line(&self.text_buf, page_size,
+                            &mut snap.memory_by_numa_node)?;
+        }
+        Ok(())
+    }
+    #[cfg(not(target_os="linux"))]
+    fn read_numa_maps(&mut self, _snap: &mut Snapshot)
+        -> Result<(), StatusError>
+    {
+        Ok(())
+    }
+
+    #[cfg(target_os="linux")]
+    fn read_fd_count(&mut self, snap: &mut Snapshot) -> Result<(), io::Error>
+    {
+        snap.fd_count = 0;
+        proc_path(&mut self.path_buf, &self.proc_root, self.pid, "fd");
+        // fd of another user's process can't be listed, count stays zero
+        let dir = match fs::read_dir(&self.path_buf) {
+            Ok(dir) => dir,
+            Err(_) => return Ok(()),
+        };
+        let mut count = 0u32;
+        // directory is closed when iterator is dropped
+        for entry in dir {
+            entry?;
+            count += 1;
+        }
+        if self.pid.is_none() {
+            // the descriptor of the directory being listed is there too
+            count = count.saturating_sub(1);
+        }
+        snap.fd_count = count;
+        Ok(())
+    }
+    #[cfg(not(target_os="linux"))]
+    fn read_fd_count(&mut self, _snap: &mut Snapshot) -> Result<(), io::Error>
+    {
+        Ok(())
+    }
+
+    #[cfg(target_os="linux")]
+    fn read_fd_types(&mut self, snap: &mut Snapshot) -> Result<(), io::Error>
+    {
+        snap.fds_by_type.clear();
+        proc_path(&mut self.path_buf, &self.proc_root, self.pid, "fd");
+        for entry in fs::read_dir(&self.path_buf)? {
+            let target = match fs::read_link(entry?.path()) {
+                Ok(target) => target,
+                // descriptor closed while we were listing
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            let kind = parse_fd_kind(&target.to_string_lossy());
+            *snap.fds_by_type.entry(kind).or_insert(0) += 1;
+        }
+        if self.pid.is_none() {
+            // the descriptor of the directory being listed, as in
+            // `read_fd_count`
+            if let Some(files) = snap.fds_by_type.get_mut(&FdKind::File) {
+                *files = files.saturating_sub(1);
+                if *files == 0 {
+                    snap.fds_by_type.remove(&FdKind::File);
+                }
+            }
+        }
+        Ok(())
+    }
+    #[cfg(not(target_os="linux"))]
+    fn read_fd_types(&mut self, _snap: &mut Snapshot) -> Result<(), io::Error>
+    {
+        Ok(())
+    }
+
+    #[cfg(target_os="linux")]
+    fn read_vmstat(&mut self, snap: &mut Snapshot)
+        -> Result<(), VmStatError>
+    {
+        snap.swap_in_pages = 0;
+        proc_file(&mut self.path_buf, &self.proc_root, "vmstat");
+        // may be hidden in sandboxes, swap-in rate is zero then
+        if read_file(&self.path_buf, &mut self.text_buf).is_err() {
+            return Ok(());
+        }
+        for line in self.text_buf.lines() {
+            let mut pairs = line.split_whitespace();
+            if let (Some("pswpin"), Some(text)) = (pairs.next(), pairs.next())
+            {
+                snap.swap_in_pages = text.parse()?;
+                break;
+            }
+        }
+        Ok(())
+    }
+    #[cfg(not(target_os="linux"))]
+    fn read_vmstat(&mut self, _snap: &mut Snapshot)
+        -> Result<(), VmStatError>
+    {
+        Ok(())
+    }
+
+    #[cfg(target_os="linux")]
+    fn read_net_dev(&mut self, snap: &mut Snapshot)
+        -> Result<(), NetDevError>
+    {
+        snap.net_rx_bytes = 0;
+        snap.net_tx_bytes = 0;
+        snap.net_rx_packets = 0;
+        snap.net_tx_packets = 0;
+        proc_path(&mut self.path_buf, &self.proc_root, self.pid, "net/dev");
+        // may be hidden in sandboxes, network counters are zero then
+        if read_file(&self.path_buf, &mut self.text_buf).is_err() {
+            return Ok(());
+        }
+        let (rx_bytes, tx_bytes, rx_packets, tx_packets) = parse_net_dev(
+            &self.text_buf, self.net_interfaces.as_ref().map(|x| &x[..]))?;
+        snap.net_rx_bytes = rx_bytes;
+        snap.net_tx_bytes = tx_bytes;
+        snap.net_rx_packets = rx_packets;
+        snap.net_tx_packets = tx_packets;
+        Ok(())
+    }
+    #[cfg(not(target_os="linux"))]
+    fn read_net_dev(&mut self, _snap: &mut Snapshot)
+        -> Result<(), NetDevError>
+    {
+        Ok(())
+    }
+
+    #[cfg(target_os="linux")]
+    fn read_meminfo(&mut self, snap: &mut Snapshot)
+        -> Result<(), StatusError>
+    {
+        snap.system_memory_total = 0;
+        snap.system_memory_free = 0;
+        snap.system_memory_available = 0;
+        proc_file(&mut self.path_buf, &self.proc_root, "meminfo");
+        // may be hidden in sandboxes, system memory is zero then
+        if read_file(&self.path_buf, &mut self.text_buf).is_err() {
+            return Ok(());
+        }
+        for line in self.text_buf.lines() {
+            let mut pairs = line.split(':');
+            match (pairs.next(), pairs.next()) {
+                (Some("MemTotal"), Some(text))
+                => snap.system_memory_total = parse_memory(text)?,
+                (Some("MemFree"), Some(text))
+                => snap.system_memory_free = parse_memory(text)?,
+                (Some("MemAvailable"), Some(text))
+                => snap.system_memory_available = parse_memory(text)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+    #[cfg(not(target_os="linux"))]
+    fn read_meminfo(&mut self, _snap: &mut Snapshot)
+        -> Result<(), StatusError>
+    {
+        Ok(())
+    }
+
+}
+
+/// Formats path of the `file` in /proc directory of the process into `buf`
+#[cfg(target_os="linux")]
+pub fn proc_path(buf: &mut String, root: &Path, pid: Option<Pid>,
+    file: &str)
+{
+    buf.truncate(0);
+    match pid {
+        Some(pid) => {
+            write!(buf, "{}/{}/{}", root.display(), pid, file).unwrap()
+        }
+        None => write!(buf, "{}/self/{}", root.display(), file).unwrap(),
+    }
+}
+
+/// Formats path of the system-wide `file` in /proc into `buf`
+#[cfg(target_os="linux")]
+pub fn proc_file(buf: &mut String, root: &Path, file: &str) {
+    buf.truncate(0);
+    write!(buf, "{}/{}", root.display(), file).unwrap();
+}
+
+/// Categorizes memory mapping by its header line in /proc/self/smaps
+///
+/// Header looks like `7f12a000-7f12b000 r-xp 00000000 08:01 123  /lib/x.so`
+#[cfg(target_os="linux")]
+fn parse_mapping_kind(header: &str) -> Option<RegionKind> {
+    let mut parts = header.split_whitespace();
+    let perms = parts.nth(1)?;
+    let path = parts.nth(3).unwrap_or("");
+    if path == "[heap]" {
+        Some(RegionKind::Heap)
+    } else if path.starts_with("[stack") {
+        Some(RegionKind::Stack)
+    } else if perms.contains('x') {
+        Some(RegionKind::Executable)
+    } else if perms.contains('w') {
+        Some(RegionKind::WritableData)
+    } else {
+        Some(RegionKind::ReadOnlyData)
+    }
+}
+
+/// Parses `cpuN` line of /proc/stat
+#[cfg(target_os="linux")]
+fn parse_core_line(line: &str) -> Result<CoreTimes, StatError> {
+    let mut iter = line.split_whitespace();
+    let id = iter.next().and_then(|x| x.strip_prefix("cpu"))
+        .ok_or(StatError::BadFormat)?
+        .parse()?;
+    let mut core = CoreTimes { id, idle: 0, total: 0 };
+    // same fields as in the aggregate line, guest is not needed
+    for (idx, value) in iter.take(8).enumerate() {
+        let value: u64 = value.parse()?;
+        if idx == 3 || idx == 4 {
+            core.idle = core.idle.saturating_add(value);
+        }
+        core.total = core.total.saturating_add(value);
+    }
+    Ok(core)
+}
+
+/// Categorizes file descriptor by target of its link in /proc/self/fd
+#[cfg(target_os="linux")]
+fn parse_fd_kind(target: &str) -> FdKind {
+    if target.starts_with('/') {
+        FdKind::File
+    } else if target.starts_with("socket:") {
+        FdKind::Socket
+    } else if target.starts_with("pipe:") {
+        FdKind::Pipe
+    } else if target == "anon_inode:[eventpoll]" {
+        FdKind::Epoll
+    } else if target == "anon_inode:[eventfd]" {
+        FdKind::EventFd
+    } else if target.starts_with("anon_inode:") {
+        FdKind::AnonInode
+    } else {
+        FdKind::Other
+    }
+}
+
+/// Adds resident bytes per node from a line of /proc/self/numa_maps
+///
+/// Line looks like `7f12a000 default file=/lib/x.so mapped=3 N0=2 N1=1
+/// kernelpagesize_kB=4`, the `page_size` is used if the latter is absent.
+#[cfg(target_os="linux")]
+fn parse_numa_line(line: &str, page_size: u64, nodes: &mut HashMap<u32, u64>)
+    -> Result<(), StatusError>
+{
+    let mut page_size = page_size;
+    for token in line.split_whitespace() {
+        if let Some(kb) = token.strip_prefix("kernelpagesize_kB=") {
+            page_size = kb.parse::<u64>()?.saturating_mul(1024);
+        }
+    }
+    for token in line.split_whitespace() {
+        if !token.starts_with('N') {
+            continue;
+        }
+        if let Some(eq) = token.find('=') {
+            let node = token[1..eq].parse()?;
+            let pages: u64 = token[eq+1..].parse()?;
+            let bytes = nodes.entry(node).or_insert(0);
+            *bytes = bytes.saturating_add(pages.saturating_mul(page_size));
+        }
+    }
+    Ok(())
+}
+
+/// Sums `(rx_bytes, tx_bytes, rx_packets, tx_packets)` of /proc/net/dev
+///
+/// Only `interfaces` are accounted if specified.
+#[cfg(target_os="linux")]
+fn parse_net_dev(text: &str, interfaces: Option<&[String]>)
+    -> Result<(u64, u64, u64, u64), NetDevError>
+{
+    let mut result = (0u64, 0u64, 0u64, 0u64);
+    // two lines of header
+    for line in text.lines().skip(2) {
+        let mut pair = line.splitn(2, ':');
+        let name = pair.next().ok_or(NetDevError::BadFormat)?.trim();
+        let values = pair.next().ok_or(NetDevError::BadFormat)?;
+        if let Some(list) = interfaces {
+            if !list.iter().any(|x| x == name) {
+                continue;
+            }
+        }
+        let mut fields = values.split_whitespace();
+        let mut field = |n| -> Result<u64, NetDevError> {
+            Ok(fields.nth(n).ok_or(NetDevError::BadFormat)?.parse()?)
+        };
+        let rx_bytes = field(0)?;
+        let rx_packets = field(0)?;
+        // skip errs, drop, fifo, frame, compressed, multicast
+        let tx_bytes = field(6)?;
+        let tx_packets = field(0)?;
+        result.0 = result.0.saturating_add(rx_bytes);
+        result.1 = result.1.saturating_add(tx_bytes);
+        result.2 = result.2.saturating_add(rx_packets);
+        result.3 = result.3.saturating_add(tx_packets);
+    }
+    Ok(result)
 }
 
+#[cfg(target_os="linux")]
 fn parse_memory(value: &str) -> Result<u64, StatusError> {
     let mut pair = value.split_whitespace();
-    let value = try!(try!(pair.next().ok_or(StatusError::BadFormat))
-        .parse::<u64>());
+    let value = pair.next().ok_or(StatusError::BadFormat)?
+        .parse::<u64>()?;
     match pair.next() {
-        Some("kB") => Ok(value * 1024),
+        Some("kB") => Ok(value.saturating_mul(1024)),
         _ => Err(StatusError::BadUnit),
     }
 }
 
+#[cfg(target_os="linux")]
 pub fn parse_uptime(value: &str) -> Result<u64, UptimeError> {
     if value.len() <= 3 {
         return Err(UptimeError::BadFormat);
@@ -177,36 +920,118 @@ pub fn parse_uptime(value: &str) -> Result<u64, UptimeError> {
     let dot = value.find('.').ok_or(UptimeError::BadFormat)?;
     let (integer, decimals) = value.split_at(dot);
     if decimals.len() == 1+1 {
-        Ok(try!(integer.parse::<u64>()) * 100 +
-           try!(decimals[1..].parse::<u64>())*10)
+        Ok(integer.parse::<u64>()?.saturating_mul(100)
+           .saturating_add(decimals[1..].parse::<u64>()?*10))
     } else if decimals.len() == 1+2 {
-        Ok(try!(integer.parse::<u64>()) * 100 +
-           try!(decimals[1..].parse::<u64>()))
+        Ok(integer.parse::<u64>()?.saturating_mul(100)
+           .saturating_add(decimals[1..].parse::<u64>()?))
     } else {
         Err(UptimeError::BadFormat)
     }
 }
 
-fn read_stat(text_buf: &mut String, path: &str, thread_info: &mut ThreadInfo)
+/// Number of attempts for a read interrupted by a signal
+#[cfg(target_os="linux")]
+const READ_ATTEMPTS: u32 = 3;
+
+/// Calls `f` again (up to `READ_ATTEMPTS` in total) while it fails with
+/// `ErrorKind::Interrupted`
+///
+/// While std retries on `EINTR` within a single call, a sequence of
+/// calls (like open and read) may still fail mid-way, so we restart it.
+#[cfg(target_os="linux")]
+fn retry_interrupted<T, F>(mut f: F) -> io::Result<T>
+    where F: FnMut() -> io::Result<T>
+{
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted
+                && attempt < READ_ATTEMPTS
+            => attempt += 1,
+            result => return result,
+        }
+    }
+}
+
+/// Reads the whole file at `path` into `buf`, replacing its contents
+#[cfg(target_os="linux")]
+pub(crate) fn read_file(path: &str, buf: &mut String) -> io::Result<()> {
+    retry_interrupted(|| {
+        buf.truncate(0);
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(buf))
+            .map(|_| ())
+    })
+}
+
+/// Rereads the file which is kept open into `buf`, replacing its contents
+#[cfg(target_os="linux")]
+fn read_open_file(file: &mut File, buf: &mut String) -> io::Result<()> {
+    use std::io::{Seek, SeekFrom};
+
+    retry_interrupted(|| {
+        buf.truncate(0);
+        file.seek(SeekFrom::Start(0))?;
+        file.read_to_string(buf).map(|_| ())
+    })
+}
+
+#[cfg(target_os="linux")]
+fn parse_stat(text_buf: &str, thread_info: &mut ThreadInfo)
     -> Result<(), StatError>
 {
-    text_buf.truncate(0);
-    try!(File::open(path)
-         .and_then(|mut f| f.read_to_string(text_buf)));
-    let right_paren = try!(text_buf.rfind(')')
-        .ok_or(StatError::BadFormat));
+    let right_paren = text_buf.rfind(')')
+        .ok_or(StatError::BadFormat)?;
     let mut iter = text_buf[right_paren+1..].split_whitespace();
-    thread_info.user_time = try!(
-        try!(iter.nth(11).ok_or(StatError::BadFormat)).parse());
-    thread_info.system_time = try!(
-        try!(iter.next().ok_or(StatError::BadFormat)).parse());
-    thread_info.child_user_time = try!(
-        try!(iter.next().ok_or(StatError::BadFormat)).parse());
-    thread_info.child_system_time = try!(
-        try!(iter.next().ok_or(StatError::BadFormat)).parse());
+    thread_info.minor_faults =
+        iter.nth(7).ok_or(StatError::BadFormat)?.parse()?;
+    thread_info.major_faults =
+        iter.nth(1).ok_or(StatError::BadFormat)?.parse()?;
+    thread_info.user_time =
+        iter.nth(1).ok_or(StatError::BadFormat)?.parse()?;
+    thread_info.system_time =
+        iter.next().ok_or(StatError::BadFormat)?.parse()?;
+    thread_info.child_user_time =
+        iter.next().ok_or(StatError::BadFormat)?.parse()?;
+    thread_info.child_system_time =
+        iter.next().ok_or(StatError::BadFormat)?.parse()?;
+    // in clock ticks, which are centiseconds like the times above
+    thread_info.start_time =
+        iter.nth(4).ok_or(StatError::BadFormat)?.parse()?;
+    thread_info.processor =
+        iter.nth(16).ok_or(StatError::BadFormat)?.parse()?;
     Ok(())
 }
 
+#[cfg(target_os="linux")]
+fn read_thread_io(text_buf: &mut String, path: &str,
+    thread_info: &mut ThreadInfo)
+    -> Result<(), IoStatError>
+{
+    read_file(path, text_buf)?;
+    for line in text_buf.lines() {
+        let mut pairs = line.split(':');
+        match (pairs.next(), pairs.next().map(|x| x.trim())) {
+            (Some("rchar"), Some(text))
+            => thread_info.read_bytes = text.parse()?,
+            (Some("wchar"), Some(text))
+            => thread_info.write_bytes = text.parse()?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Parses `<run_ns> <wait_ns> <timeslices>` of schedstat
+#[cfg(target_os="linux")]
+fn parse_schedstat(text: &str) -> Option<(u64, u64)> {
+    let mut values = text.split_whitespace();
+    let run = values.next()?.parse().ok()?;
+    let wait = values.next()?.parse().ok()?;
+    Some((run, wait))
+}
+
 impl ThreadInfo {
     fn new() -> ThreadInfo {
         ThreadInfo {
@@ -214,22 +1039,44 @@ impl ThreadInfo {
             system_time: 0,
             child_user_time: 0,
             child_system_time: 0,
+            minor_faults: 0,
+            major_faults: 0,
+            read_bytes: 0,
+            write_bytes: 0,
+            start_time: 0,
+            run_time_ns: 0,
+            wait_time_ns: 0,
+            processor: 0,
         }
     }
 }
 
 impl Snapshot {
-    fn new(threads: &HashMap<Pid, String>) -> Snapshot {
+    pub fn new(threads: &HashMap<Pid, String>) -> Snapshot {
         Snapshot {
             timestamp: SystemTime::now(),
             instant: Instant::now(),
             uptime: 0,
             idle_time: 0,
+            stat_idle: 0,
+            stat_total: 0,
+            stat_iowait: 0,
+            stat_steal: 0,
+            stat_guest: 0,
+            cores: Vec::new(),
             process: ThreadInfo::new(),
             memory_rss: 0,
             memory_virtual: 0,
             memory_virtual_peak: 0,
+            memory_rss_hwm: 0,
             memory_swap: 0,
+            memory_rss_anon: 0,
+            memory_rss_file: 0,
+            memory_rss_shmem: 0,
+            memory_data: 0,
+            memory_stack: 0,
+            memory_exe: 0,
+            memory_lib: 0,
             read_bytes: 0,
             write_bytes: 0,
             read_ops: 0,
@@ -237,6 +1084,22 @@ impl Snapshot {
             read_disk_bytes: 0,
             write_disk_bytes: 0,
             write_cancelled_bytes: 0,
+            net_rx_bytes: 0,
+            net_tx_bytes: 0,
+            net_rx_packets: 0,
+            net_tx_packets: 0,
+            swap_in_pages: 0,
+            system_memory_total: 0,
+            system_memory_available: 0,
+            system_memory_free: 0,
+            memory_by_region: HashMap::new(),
+            memory_by_numa_node: HashMap::new(),
+            fds_by_type: HashMap::new(),
+            num_threads: 0,
+            fd_count: 0,
+            voluntary_ctxt_switches: 0,
+            nonvoluntary_ctxt_switches: 0,
+            tracer_pid: 0,
             threads: threads.iter()
                 .map(|(&pid, _)| (pid, ThreadInfo::new()))
                 .collect(),
@@ -246,14 +1109,361 @@ impl Snapshot {
 
 #[cfg(test)]
 mod test {
-    use super::parse_uptime;
+    #[cfg(target_os="linux")] use std::collections::HashMap;
+    use std::time::Duration;
+
+    use Meter;
+    #[cfg(target_os="linux")] use {MeterBuilder, Snapshot, CpuSource};
+    #[cfg(target_os="linux")]
+    use super::{parse_uptime, parse_mapping_kind, parse_numa_line};
+    #[cfg(target_os="linux")]
+    use super::{parse_memory, parse_fd_kind, parse_core_line, parse_net_dev};
+    #[cfg(target_os="linux")]
+    use super::{parse_schedstat, retry_interrupted};
+    #[cfg(target_os="linux")] use {RegionKind, FdKind};
+
+    #[cfg(target_os="linux")]
+    fn fixture_meter() -> Meter {
+        MeterBuilder::new(Duration::new(1, 0))
+            .proc_root(concat!(env!("CARGO_MANIFEST_DIR"),
+                               "/tests/fixtures/proc"))
+            .build().unwrap()
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn fixture_cpu_times() {
+        let mut meter = fixture_meter();
+        meter.track_thread(4243, "worker");
+        let mut snap = Snapshot::new(&meter.thread_names);
+        meter.read_cpu_times(&mut snap.process, &mut snap.threads,
+            &mut snap.uptime, &mut snap.idle_time).unwrap();
+        assert_eq!(snap.uptime, 1234567);
+        assert_eq!(snap.idle_time, 4567890);
+        assert_eq!(snap.process.minor_faults, 1500);
+        assert_eq!(snap.process.major_faults, 7);
+        assert_eq!(snap.process.user_time, 250);
+        assert_eq!(snap.process.system_time, 75);
+        assert_eq!(snap.process.child_user_time, 10);
+        assert_eq!(snap.process.child_system_time, 5);
+        assert_eq!(snap.process.start_time, 98765);
+        assert_eq!(snap.process.processor, 2);
+        let thread = &snap.threads[&4243];
+        assert_eq!(thread.user_time, 120);
+        assert_eq!(thread.system_time, 30);
+        assert_eq!(thread.processor, 3);
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn fixture_scan() {
+        // fixture has no /proc/stat, vmstat, meminfo and fd directory
+        let mut meter = fixture_meter();
+        meter.scan().unwrap();
+        meter.scan().unwrap();
+        let report = meter.report().unwrap();
+        assert_eq!(report.global_cpu_source, CpuSource::Uptime);
+        assert_eq!(report.memory_rss, 10240 << 10);
+        assert_eq!(report.system_memory_total, 0);
+        assert_eq!(report.fd_count, 0);
+        assert_eq!(report.net_read, 0.);
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn fixture_pid_in_root() {
+        let mut meter = Meter::new_for_pid_in_root(4242,
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/proc"),
+            Duration::new(1, 0)).unwrap();
+        let mut snap = Snapshot::new(&HashMap::new());
+        meter.read_cpu_times(&mut snap.process, &mut snap.threads,
+            &mut snap.uptime, &mut snap.idle_time).unwrap();
+        assert_eq!(snap.uptime, 1234567);
+        assert_eq!(snap.process.user_time, 500);
+        assert_eq!(snap.process.system_time, 125);
+        assert_eq!(snap.process.major_faults, 2);
+        meter.read_memory(&mut snap).unwrap();
+        assert_eq!(snap.memory_rss, 10240 << 10);
+        assert!(Meter::new_for_pid_in_root(4243,
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/proc"),
+            Duration::new(1, 0)).is_err());
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn fixture_memory() {
+        let mut meter = fixture_meter();
+        let mut snap = Snapshot::new(&HashMap::new());
+        meter.read_memory(&mut snap).unwrap();
+        assert_eq!(snap.memory_rss, 10240 << 10);
+        assert_eq!(snap.memory_rss_hwm, 20480 << 10);
+        assert_eq!(snap.memory_rss_anon, 6144 << 10);
+        assert_eq!(snap.memory_rss_file, 3072 << 10);
+        assert_eq!(snap.memory_rss_shmem, 1024 << 10);
+        assert_eq!(snap.memory_virtual, 153600 << 10);
+        assert_eq!(snap.memory_virtual_peak, 204800 << 10);
+        assert_eq!(snap.memory_swap, 512 << 10);
+        assert_eq!(snap.memory_data, 40960 << 10);
+        assert_eq!(snap.memory_stack, 132 << 10);
+        assert_eq!(snap.memory_exe, 2048 << 10);
+        assert_eq!(snap.memory_lib, 8192 << 10);
+        assert_eq!(snap.num_threads, 3);
+        assert_eq!(snap.voluntary_ctxt_switches, 1000);
+        assert_eq!(snap.nonvoluntary_ctxt_switches, 25);
+        assert_eq!(snap.tracer_pid, 0);
+    }
+
+    #[test]
+    #[cfg(all(target_os="linux", feature="io-stats"))]
+    fn fixture_io() {
+        let mut meter = fixture_meter();
+        let mut snap = Snapshot::new(&HashMap::new());
+        meter.read_io(&mut snap).unwrap();
+        assert_eq!(snap.read_bytes, 1048576);
+        assert_eq!(snap.write_bytes, 524288);
+        assert_eq!(snap.read_ops, 300);
+        assert_eq!(snap.write_ops, 200);
+        assert_eq!(snap.read_disk_bytes, 65536);
+        assert_eq!(snap.write_disk_bytes, 32768);
+        assert_eq!(snap.write_cancelled_bytes, 4096);
+    }
 
     #[test]
+    #[cfg(target_os="linux")]
+    fn fixture_net_dev() {
+        let mut meter = fixture_meter();
+        let mut snap = Snapshot::new(&HashMap::new());
+        meter.read_net_dev(&mut snap).unwrap();
+        assert_eq!(snap.net_rx_bytes, 1048576 + 8192);
+        assert_eq!(snap.net_tx_bytes, 524288 + 8192);
+        assert_eq!(snap.net_rx_packets, 1064);
+        assert_eq!(snap.net_tx_packets, 564);
+        meter.set_net_interfaces(Some(vec!["eth0".to_string()]));
+        meter.read_net_dev(&mut snap).unwrap();
+        assert_eq!(snap.net_rx_bytes, 1048576);
+        assert_eq!(snap.net_tx_packets, 500);
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn net_dev_bad_format() {
+        let header = "Inter-|\n face |\n";
+        assert!(parse_net_dev(&format!("{}  eth0: 1 2 3", header), None)
+                .is_err());
+        assert_eq!(parse_net_dev(header, None).unwrap(), (0, 0, 0, 0));
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn interrupted() {
+        use std::io;
+
+        let mut calls = 0;
+        let result = retry_interrupted(|| {
+            calls += 1;
+            if calls < 3 {
+                Err(io::Error::from(io::ErrorKind::Interrupted))
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        let mut calls = 0;
+        let result: io::Result<()> = retry_interrupted(|| {
+            calls += 1;
+            Err(io::Error::from(io::ErrorKind::Interrupted))
+        });
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Interrupted);
+        assert_eq!(calls, 3);
+
+        let mut calls = 0;
+        let result: io::Result<()> = retry_interrupted(|| {
+            calls += 1;
+            Err(io::Error::from(io::ErrorKind::NotFound))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn schedstat() {
+        assert_eq!(parse_schedstat("2060108905 58367176 1530\n"),
+                   Some((2060108905, 58367176)));
+        assert_eq!(parse_schedstat(""), None);
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn fixture_schedstat() {
+        let mut meter = fixture_meter();
+        meter.track_thread(4243, "worker");
+        meter.track_thread(4244, "no_schedstat");
+        let mut snap = Snapshot::new(&meter.thread_names);
+        meter.read_thread_schedstat(&mut snap);
+        assert_eq!(snap.threads[&4243].run_time_ns, 1500000000);
+        assert_eq!(snap.threads[&4243].wait_time_ns, 250000000);
+        assert_eq!(snap.threads[&4244].run_time_ns, 0);
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn scan_and_notify() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        meter.track_current_thread("main");
+        let mut calls = Vec::new();
+        for _ in 0..3 {
+            meter.scan_and_notify(|report, threads| {
+                calls.push((report.num_threads > 0, threads.count()));
+            }).unwrap();
+        }
+        assert_eq!(calls, vec![(true, 1), (true, 1)]);
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn fd_types_sum() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        meter.enable_fd_types(true);
+        // other tests open files concurrently, so a single scan may race
+        for _ in 0..10 {
+            meter.scan().unwrap();
+            let snap = meter.snapshots.back().unwrap();
+            if snap.fds_by_type.values().sum::<u32>() == snap.fd_count {
+                return;
+            }
+        }
+        panic!("fds_by_type doesn't sum to fd_count");
+    }
+
+    #[test]
+    fn scan_and_notify_throttled() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        meter.set_report_throttle(Duration::new(3600, 0));
+        let mut calls = 0;
+        for _ in 0..3 {
+            meter.scan_and_notify(|_, _| calls += 1).unwrap();
+        }
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn thread_stat_files() {
+        let mut meter = fixture_meter();
+        meter.track_thread(4243, "worker");
+        let mut snap = Snapshot::new(&meter.thread_names);
+        meter.read_cpu_times(&mut snap.process, &mut snap.threads,
+            &mut snap.uptime, &mut snap.idle_time).unwrap();
+        assert!(meter.thread_stat_files.contains_key(&4243));
+        // cached file is reused
+        meter.read_cpu_times(&mut snap.process, &mut snap.threads,
+            &mut snap.uptime, &mut snap.idle_time).unwrap();
+        assert_eq!(snap.threads[&4243].user_time, 120);
+        meter.untrack_thread(4243);
+        assert!(meter.thread_stat_files.is_empty());
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn exited_thread() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        let tid = meter.track_current_thread("main");
+        // larger than maximum pid_max, so task directory never exists
+        meter.track_thread(999_999_999, "exited");
+        meter.scan().unwrap();
+        assert!(!meter.thread_names.contains_key(&999_999_999));
+        assert!(meter.thread_names.contains_key(&tid));
+        let snap = meter.snapshots.back().unwrap();
+        assert!(!snap.threads.contains_key(&999_999_999));
+        assert!(snap.threads.contains_key(&tid));
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn process_age() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        assert!(meter.process_age().is_none());
+        meter.scan().unwrap();
+        let snap = meter.snapshots.back().unwrap();
+        assert!(snap.process.start_time > 0);
+        // test binary is surely started less than an hour ago
+        assert!(meter.process_age().unwrap() < Duration::new(3600, 0));
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
     fn normal_uptime() {
         assert_eq!(parse_uptime("1927830.69").unwrap(), 192783069);
     }
     #[test]
+    #[cfg(target_os="linux")]
     fn one_zero_uptime() {
         assert_eq!(parse_uptime("4780.0").unwrap(), 478000);
     }
+    #[test]
+    #[cfg(target_os="linux")]
+    fn huge_uptime() {
+        assert_eq!(parse_uptime("184467440737095516.15").unwrap(), u64::MAX);
+        assert!(parse_uptime("999999999999999999999.9").is_err());
+    }
+    #[test]
+    #[cfg(target_os="linux")]
+    fn huge_memory() {
+        assert_eq!(parse_memory(" 18446744073709551615 kB").unwrap(),
+                   u64::MAX);
+    }
+    #[test]
+    #[cfg(target_os="linux")]
+    fn mapping_kinds() {
+        assert_eq!(parse_mapping_kind(
+            "5581d000-5581e000 r-xp 00002000 08:01 1318 /usr/bin/cat\n"),
+            Some(RegionKind::Executable));
+        assert_eq!(parse_mapping_kind(
+            "5581f000-55820000 rw-p 00000000 00:00 0 \n"),
+            Some(RegionKind::WritableData));
+        assert_eq!(parse_mapping_kind(
+            "7f1e2000-7f1e4000 r--p 00000000 08:01 1318 /usr/bin/cat\n"),
+            Some(RegionKind::ReadOnlyData));
+        assert_eq!(parse_mapping_kind(
+            "55822000-55843000 rw-p 00000000 00:00 0    [heap]\n"),
+            Some(RegionKind::Heap));
+        assert_eq!(parse_mapping_kind(
+            "7ffd1000-7ffd2000 rw-p 00000000 00:00 0    [stack]\n"),
+            Some(RegionKind::Stack));
+    }
+    #[test]
+    #[cfg(target_os="linux")]
+    fn core_line() {
+        let core = parse_core_line(
+            "cpu3 4705 150 1120 16250 520 20 33 0 0 0").unwrap();
+        assert_eq!(core.id, 3);
+        assert_eq!(core.idle, 16770);
+        assert_eq!(core.total, 22798);
+    }
+    #[test]
+    #[cfg(target_os="linux")]
+    fn fd_kinds() {
+        assert_eq!(parse_fd_kind("/dev/null"), FdKind::File);
+        assert_eq!(parse_fd_kind("socket:[1202]"), FdKind::Socket);
+        assert_eq!(parse_fd_kind("pipe:[7314]"), FdKind::Pipe);
+        assert_eq!(parse_fd_kind("anon_inode:[eventpoll]"), FdKind::Epoll);
+        assert_eq!(parse_fd_kind("anon_inode:[eventfd]"), FdKind::EventFd);
+        assert_eq!(parse_fd_kind("anon_inode:inotify"), FdKind::AnonInode);
+        assert_eq!(parse_fd_kind("net:[4026531840]"), FdKind::Other);
+    }
+    #[test]
+    #[cfg(target_os="linux")]
+    fn numa_line() {
+        let mut nodes = HashMap::new();
+        parse_numa_line("55c1e000 default file=/usr/bin/cat mapped=5 \
+            N0=3 N1=2 kernelpagesize_kB=4\n", 4096, &mut nodes).unwrap();
+        parse_numa_line("7f001000 default anon=1 dirty=1 N1=1\n",
+            4096, &mut nodes).unwrap();
+        parse_numa_line("7f200000 default huge anon=1 dirty=1 N0=1 \
+            kernelpagesize_kB=2048\n", 4096, &mut nodes).unwrap();
+        assert_eq!(nodes.get(&0), Some(&(3*4096 + (2 << 20))));
+        assert_eq!(nodes.get(&1), Some(&(3*4096)));
+    }
 }
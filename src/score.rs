@@ -0,0 +1,172 @@
+use {Meter, Report};
+
+
+/// Weights and ceilings used by `Report::composite_score`
+///
+/// Use `ScoreWeights::default()` and update the fields you need. Weights
+/// may be stored in the meter with `Meter::set_score_weights`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreWeights {
+    /// Weight of process' CPU usage, default is `1.0`
+    pub cpu: f32,
+    /// Weight of resident memory, default is `1.0`
+    pub memory: f32,
+    /// Weight of block IO, default is `1.0`
+    pub io: f32,
+    /// Weight of memory pressure (`thrashing_indicator`), default is `1.0`
+    pub pressure: f32,
+    /// CPU usage which counts as saturated (100% is a single core),
+    /// default is `100.0`
+    pub cpu_ceiling: f32,
+    /// Resident memory in bytes which counts as saturated, default is 1 GiB
+    pub memory_ceiling: u64,
+    /// Block IO (read plus written) bytes per second which counts as
+    /// saturated, default is 50 MiB
+    pub io_ceiling: f32,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> ScoreWeights {
+        ScoreWeights {
+            cpu: 1.,
+            memory: 1.,
+            io: 1.,
+            pressure: 1.,
+            cpu_ceiling: 100.,
+            memory_ceiling: 1 << 30,
+            io_ceiling: (50 << 20) as f32,
+        }
+    }
+}
+
+/// Components of `Report::memory_pressure_score`, each from 0 to 1
+///
+/// Returned by `Report::memory_pressure` so the score can be recomputed
+/// with other weights.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemoryPressure {
+    /// Resident memory as a fraction of the cgroup memory limit
+    pub rss: f32,
+    /// Swapped out memory as a fraction of the cgroup memory limit
+    pub swap: f32,
+    /// Major page faults per second normalized against 100 per second
+    pub major_faults: f32,
+}
+
+impl MemoryPressure {
+    /// Returns `0.5 * rss + 0.25 * swap + 0.25 * major_faults`
+    pub fn score(&self) -> f32 {
+        0.5 * self.rss + 0.25 * self.swap + 0.25 * self.major_faults
+    }
+}
+
+/// Normalizes value against the ceiling into `[0, 1]`
+fn saturation(value: f32, ceiling: f32) -> f32 {
+    if ceiling > 0. {
+        (value / ceiling).clamp(0., 1.)
+    } else {
+        0.
+    }
+}
+
+impl Meter {
+    /// Store weights to pass to `Report::composite_score`
+    pub fn set_score_weights(&mut self, weights: ScoreWeights) {
+        self.score_weights = weights;
+    }
+    /// Returns weights set by `set_score_weights`, defaults if unset
+    pub fn score_weights(&self) -> &ScoreWeights {
+        &self.score_weights
+    }
+}
+
+impl Report {
+    /// Returns resource stress score from 0 (idle) to 1 (saturated)
+    ///
+    /// Each of CPU, memory, IO and memory pressure is normalized against
+    /// its ceiling (and clamped to 1), then weighted average is taken.
+    /// So single resource saturating raises the score proportionally to
+    /// its weight. Returns zero if all the weights are zero. Pass
+    /// `Meter::score_weights()` to use the weights stored in the meter.
+    pub fn composite_score(&self, weights: &ScoreWeights) -> f32 {
+        let w = weights;
+        let total = w.cpu + w.memory + w.io + w.pressure;
+        if total <= 0. {
+            return 0.;
+        }
+        let sum =
+            w.cpu * saturation(self.process_cpu_usage, w.cpu_ceiling) +
+            w.memory * saturation(self.memory_rss as f32,
+                                  w.memory_ceiling as f32) +
+            w.io * saturation(self.disk_read + self.disk_write,
+                              w.io_ceiling) +
+            w.pressure * self.thrashing_indicator.clamp(0., 1.);
+        sum / total
+    }
+    /// Returns components of `memory_pressure_score`
+    ///
+    /// Returns `None` if the cgroup memory limit isn't known.
+    pub fn memory_pressure(&self) -> Option<MemoryPressure> {
+        let limit = self.memory_limit? as f32;
+        Some(MemoryPressure {
+            rss: saturation(self.memory_rss as f32, limit),
+            swap: saturation(self.memory_swap as f32, limit),
+            major_faults: saturation(self.major_faults, 100.),
+        })
+    }
+    /// Returns memory pressure score from 0 (none) to 1 (severe)
+    ///
+    /// The score is `0.5 * rss + 0.25 * swap + 0.25 * major_faults`, where:
+    ///
+    /// * `rss` is `memory_rss / memory_limit`
+    /// * `swap` is `memory_swap / memory_limit`
+    /// * `major_faults` is `major_faults / 100` (per second)
+    ///
+    /// Each component is clamped to `[0, 1]`, see `memory_pressure` for
+    /// their values. So a process near its limit scores about 0.5 and
+    /// swapping or faulting on top of that raises the score further.
+    /// Returns `None` if the cgroup memory limit isn't known (i.e. memory
+    /// is unlimited or not in a container).
+    pub fn memory_pressure_score(&self) -> Option<f32> {
+        self.memory_pressure().map(|p| p.score())
+    }
+    /// Returns fraction of `gross_cpu_usage` spent in reaped children
+    ///
+    /// Computed as `(gross_cpu_usage - process_cpu_usage) / gross_cpu_usage`
+    /// clamped to `[0, 1]`. Returns zero if gross usage is zero, and also
+    /// when child CPU is excluded by `MeterBuilder::include_child_cpu`.
+    pub fn child_cpu_fraction(&self) -> f32 {
+        if self.gross_cpu_usage <= 0. {
+            return 0.;
+        }
+        ((self.gross_cpu_usage - self.process_cpu_usage)
+            / self.gross_cpu_usage).clamp(0., 1.)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use report::test::{snapshot, meter_with};
+    use super::ScoreWeights;
+
+    #[test]
+    fn composite_score() {
+        let mut meter = meter_with(snapshot(100, 0), snapshot(200, 0));
+        let mut report = meter.report().unwrap();
+        report.process_cpu_usage = 50.;
+        report.memory_rss = 2 << 30;
+        report.disk_read = 0.;
+        report.disk_write = 0.;
+        report.thrashing_indicator = 0.;
+        let weights = ScoreWeights::default();
+        // cpu is half-saturated, memory saturated, io and pressure idle
+        assert_eq!(report.composite_score(&weights), 1.5 / 4.);
+        let cpu_only = ScoreWeights { memory: 0., io: 0., pressure: 0.,
+                                      ..weights };
+        assert_eq!(report.composite_score(&cpu_only), 0.5);
+        let zero = ScoreWeights { cpu: 0., ..cpu_only };
+        assert_eq!(report.composite_score(&zero), 0.);
+        meter.set_score_weights(cpu_only);
+        assert_eq!(meter.score_weights(), &cpu_only);
+    }
+}
@@ -1,17 +1,9 @@
 use std::time::{SystemTime, Duration, UNIX_EPOCH};
 
-use serde::Serializer;
+use serde::{Serializer, Deserializer, Deserialize};
 
+use format::{tstamp_to_ms, duration_to_ms};
 
-fn tstamp_to_ms(tm: SystemTime) -> u64 {
-    let ts = tm.duration_since(UNIX_EPOCH)
-        .expect("timestamp is always after unix epoch");
-    return ts.as_secs()*1000 + (ts.subsec_nanos() / 1000000) as u64;
-}
-
-fn duration_to_ms(dur: Duration) -> u64 {
-    return dur.as_secs()*1000 + (dur.subsec_nanos() / 1000000) as u64;
-}
 
 pub fn serialize_timestamp<S>(tm: &SystemTime, ser: S)
     -> Result<S::Ok, S::Error>
@@ -26,3 +18,17 @@ pub fn serialize_duration<S>(tm: &Duration, ser: S)
 {
     ser.serialize_u64(duration_to_ms(*tm))
 }
+
+pub fn deserialize_timestamp<'de, D>(de: D) -> Result<SystemTime, D::Error>
+    where D: Deserializer<'de>
+{
+    let ms = u64::deserialize(de)?;
+    Ok(UNIX_EPOCH + Duration::from_millis(ms))
+}
+
+pub fn deserialize_duration<'de, D>(de: D) -> Result<Duration, D::Error>
+    where D: Deserializer<'de>
+{
+    let ms = u64::deserialize(de)?;
+    Ok(Duration::from_millis(ms))
+}
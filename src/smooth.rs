@@ -0,0 +1,141 @@
+use std::time::Instant;
+
+use {Meter, Report};
+
+
+/// Exponentially weighted average of a rate field
+fn ewma(alpha: f32, prev: f32, value: f32) -> f32 {
+    alpha * value + (1. - alpha) * prev
+}
+
+impl Meter {
+    /// Returns report where rate fields are exponentially smoothed
+    ///
+    /// Smoothing is applied to CPU usage, IO, disk and network throughput,
+    /// context switches and page faults. Other fields (memory, number of
+    /// threads, peaks, and so on) are passed through unchanged from
+    /// `report()`.
+    ///
+    /// `alpha` in `(0, 1]` controls responsiveness: `1` returns the raw
+    /// values, smaller values give more weight to the history. Each scan
+    /// is accounted once, so calling this method more often than `scan()`
+    /// is fine, but it must be called after each scan for the average to
+    /// be correct. State is reset by `reset_peaks()` and when snapshots
+    /// are discarded (e.g. by `resume()`), the first report after that
+    /// is returned as is.
+    ///
+    /// Values above `1` are clamped to it. Zero, negative and NaN `alpha`
+    /// would freeze the average, so they are treated as `1` (raw values).
+    pub fn smoothed_report(&mut self, alpha: f32) -> Option<Report> {
+        let alpha = if alpha > 0. { alpha.min(1.) } else { 1. };
+        let instant = self.snapshots.back()?.instant;
+        if let Some((when, ref smoothed)) = self.smoothed {
+            if when == instant {
+                return Some(smoothed.clone());
+            }
+        }
+        let mut report = self.report()?;
+        if self.snapshots.len() < 2 {
+            // warmup report is not a real sample
+            return Some(report);
+        }
+        if let Some((_, ref prev)) = self.smoothed {
+            for (value, old) in &mut [
+                (&mut report.global_cpu_usage, prev.global_cpu_usage),
+                (&mut report.global_iowait, prev.global_iowait),
+                (&mut report.global_steal, prev.global_steal),
+                (&mut report.global_guest, prev.global_guest),
+                (&mut report.process_cpu_usage, prev.process_cpu_usage),
+                (&mut report.process_user_cpu, prev.process_user_cpu),
+                (&mut report.process_system_cpu, prev.process_system_cpu),
+                (&mut report.gross_cpu_usage, prev.gross_cpu_usage),
+                (&mut report.parallelism, prev.parallelism),
+                (&mut report.voluntary_ctxt_switches,
+                    prev.voluntary_ctxt_switches),
+                (&mut report.involuntary_ctxt_switches,
+                    prev.involuntary_ctxt_switches),
+                (&mut report.disk_read, prev.disk_read),
+                (&mut report.disk_write, prev.disk_write),
+                (&mut report.disk_cancelled, prev.disk_cancelled),
+                (&mut report.io_read, prev.io_read),
+                (&mut report.io_write, prev.io_write),
+                (&mut report.io_read_ops, prev.io_read_ops),
+                (&mut report.io_write_ops, prev.io_write_ops),
+                (&mut report.net_read, prev.net_read),
+                (&mut report.net_write, prev.net_write),
+                (&mut report.net_read_packets, prev.net_read_packets),
+                (&mut report.net_write_packets, prev.net_write_packets),
+                (&mut report.minor_faults, prev.minor_faults),
+                (&mut report.major_faults, prev.major_faults),
+            ] {
+                **value = ewma(alpha, *old, **value);
+            }
+        }
+        self.smoothed = Some((instant, report.clone()));
+        Some(report)
+    }
+    pub(crate) fn reset_smoothing(&mut self) {
+        self.smoothed = None;
+    }
+}
+
+/// State of `smoothed_report`: instant of the last accounted snapshot and
+/// the smoothed report
+pub(crate) type Smoothed = Option<(Instant, Report)>;
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use Meter;
+    use report::test::snapshot;
+
+    #[test]
+    fn step() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        meter.set_num_cpus(4);
+        let snap = snapshot(100, 0);
+        let mut instant = snap.instant;
+        meter.snapshots.push_back(snap);
+        let mut user_time = 0;
+        let mut values = Vec::new();
+        for i in 1..30 {
+            let mut snap = snapshot(100 + i * 100, 0);
+            instant += Duration::new(1, 0);
+            snap.instant = instant;
+            if i > 5 {
+                // step from idle to a full core
+                user_time += 100;
+            }
+            snap.process.user_time = user_time;
+            snap.memory_rss = i * 1000;
+            meter.snapshots.push_back(snap);
+            if meter.snapshots.len() > 2 {
+                meter.snapshots.pop_front();
+            }
+            let report = meter.smoothed_report(0.3).unwrap();
+            assert_eq!(report.memory_rss, i * 1000);
+            values.push(report.process_cpu_usage);
+        }
+        assert_eq!(values[4], 0.);
+        assert!(values[5] > 29. && values[5] < 31.);
+        assert!(values.windows(2).all(|w| w[0] <= w[1]));
+        assert!((values[28] - 100.).abs() < 0.1);
+        // repeated call doesn't account the same sample twice
+        let again = meter.smoothed_report(0.3).unwrap();
+        assert_eq!(again.process_cpu_usage, values[28]);
+
+        meter.reset_peaks();
+        let raw = meter.smoothed_report(0.3).unwrap();
+        assert_eq!(raw.process_cpu_usage, 100.);
+
+        // zero alpha returns raw values instead of freezing the average
+        let mut snap = snapshot(3100, 0);
+        snap.instant = instant + Duration::new(1, 0);
+        snap.process.user_time = user_time;
+        meter.snapshots.push_back(snap);
+        meter.snapshots.pop_front();
+        let raw = meter.smoothed_report(0.).unwrap();
+        assert_eq!(raw.process_cpu_usage, 0.);
+    }
+}
@@ -0,0 +1,204 @@
+#[cfg(target_os="linux")] use std::fs;
+#[cfg(target_os="linux")] use std::fmt::Write;
+use std::io;
+use std::collections::hash_map::Iter;
+
+use {Meter, Error, Pid};
+#[cfg(target_os="linux")] use scan::{proc_path, read_file};
+
+
+/// Iterator over tracked threads returned by ``Meter::tracked_threads``
+pub struct TrackedThreadsIter<'a> {
+    threads: Iter<'a, Pid, String>,
+}
+
+impl Meter {
+    /// Returns true if thread is tracked
+    pub fn is_tracking(&self, tid: Pid) -> bool {
+        self.thread_names.contains_key(&tid)
+    }
+    /// Returns iterator over tracked threads' ids and names
+    ///
+    /// Order of threads is unspecified.
+    pub fn tracked_threads(&self) -> TrackedThreadsIter<'_> {
+        TrackedThreadsIter {
+            threads: self.thread_names.iter(),
+        }
+    }
+    /// Start tracking all threads of the process which aren't tracked yet
+    ///
+    /// Threads are listed in /proc/self/task and named by their `comm`
+    /// (the name set by `std::thread::Builder::name` or `prctl`, truncated
+    /// to 15 bytes by the kernel). Threads which are already tracked keep
+    /// their names. Calling this before each `scan()` keeps dynamically
+    /// spawned threads (like in thread pools) tracked.
+    ///
+    /// Non-linux is not supported yet (no-op).
+    #[cfg(target_os="linux")]
+    pub fn track_all_threads(&mut self) -> Result<(), Error> {
+        proc_path(&mut self.path_buf, &self.proc_root, self.pid, "task");
+        let entries = fs::read_dir(&self.path_buf)
+            .map_err(Error::ThreadList)?;
+        for entry in entries {
+            let entry = entry.map_err(Error::ThreadList)?;
+            let tid = match entry.file_name().to_str()
+                .and_then(|x| x.parse().ok())
+            {
+                Some(tid) => tid,
+                None => continue,
+            };
+            if self.is_tracking(tid) {
+                continue;
+            }
+            match self.read_comm(tid) {
+                Ok(name) => self.track_thread(tid, &name),
+                // thread exited while we were listing
+                Err(ref e) if is_thread_gone(e) => continue,
+                Err(e) => return Err(Error::ThreadList(e)),
+            }
+        }
+        Ok(())
+    }
+    /// Start tracking a thread named by the kernel
+    ///
+    /// Name is read from /proc/self/task/<TID>/comm, i.e. it's the name set
+    /// by `std::thread::Builder::name` or `pthread_setname_np` (truncated
+    /// to 15 bytes by the kernel).
+    ///
+    /// Non-linux is not supported yet (returns `Unsupported` error).
+    #[cfg(target_os="linux")]
+    pub fn track_thread_auto(&mut self, tid: Pid) -> io::Result<()> {
+        let name = self.read_comm(tid)?;
+        self.track_thread(tid, &name);
+        Ok(())
+    }
+    /// Start tracking a thread named by the kernel
+    ///
+    /// Non-linux is not supported yet (returns `Unsupported` error).
+    #[cfg(not(target_os="linux"))]
+    pub fn track_thread_auto(&mut self, _tid: Pid) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+    /// Re-read names of all tracked threads from their `comm`
+    ///
+    /// This picks up names changed by `pthread_setname_np` since threads
+    /// were tracked. Note that names passed to `track_thread` are replaced
+    /// too. Statistics of threads are kept, like with `rename_thread`.
+    /// Threads which have exited are skipped.
+    ///
+    /// Non-linux is not supported yet (no-op).
+    #[cfg(target_os="linux")]
+    pub fn refresh_thread_names(&mut self) -> io::Result<()> {
+        let tids = self.thread_names.keys().cloned().collect::<Vec<_>>();
+        for tid in tids {
+            match self.read_comm(tid) {
+                Ok(name) => {
+                    self.rename_thread(tid, &name);
+                }
+                Err(ref e) if is_thread_gone(e) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+    /// Re-read names of all tracked threads from their `comm`
+    ///
+    /// Non-linux is not supported yet (no-op).
+    #[cfg(not(target_os="linux"))]
+    pub fn refresh_thread_names(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+    /// Reads name of the thread from /proc/self/task/<TID>/comm
+    #[cfg(target_os="linux")]
+    fn read_comm(&mut self, tid: Pid) -> io::Result<String> {
+        proc_path(&mut self.path_buf, &self.proc_root, self.pid, "task");
+        write!(&mut self.path_buf, "/{}/comm", tid).unwrap();
+        read_file(&self.path_buf, &mut self.text_buf)?;
+        Ok(self.text_buf.trim_end_matches('\n').to_string())
+    }
+    /// Start tracking all threads of the process which aren't tracked yet
+    ///
+    /// Non-linux is not supported yet (no-op).
+    #[cfg(not(target_os="linux"))]
+    pub fn track_all_threads(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for TrackedThreadsIter<'a> {
+    type Item = (Pid, &'a str);
+    fn next(&mut self) -> Option<(Pid, &'a str)> {
+        self.threads.next().map(|(&tid, name)| (tid, &name[..]))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.threads.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for TrackedThreadsIter<'a> {}
+
+/// Returns true if error means that thread has exited
+#[cfg(target_os="linux")]
+pub(crate) fn is_thread_gone(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::NotFound ||
+        e.raw_os_error() == Some(::libc::ESRCH)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use Meter;
+
+    #[test]
+    fn tracked_threads() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        meter.track_thread(1, "main");
+        meter.track_thread(2, "worker");
+        assert!(meter.is_tracking(2));
+        assert!(!meter.is_tracking(3));
+        let mut threads = meter.tracked_threads().collect::<Vec<_>>();
+        threads.sort();
+        assert_eq!(threads, vec![(1, "main"), (2, "worker")]);
+    }
+
+    #[test]
+    fn rename_and_untrack_all() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        meter.track_thread(1, "main");
+        assert!(meter.rename_thread(1, "renamed"));
+        assert!(!meter.rename_thread(2, "missing"));
+        assert_eq!(meter.thread_names[&1], "renamed");
+        meter.untrack_all_threads();
+        assert_eq!(meter.tracked_threads().len(), 0);
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn track_all_threads() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        let tid = meter.track_current_thread("main");
+        meter.track_all_threads().unwrap();
+        assert_eq!(meter.thread_names.get(&tid).map(|x| &x[..]),
+                   Some("main"));
+        let tracked = meter.thread_names.len();
+        assert!(tracked >= 1);
+        meter.track_all_threads().unwrap();
+        assert_eq!(meter.thread_names.len(), tracked);
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn refresh_thread_names() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        let tid = meter.track_current_thread("");
+        meter.refresh_thread_names().unwrap();
+        let name = meter.thread_names[&tid].clone();
+        assert!(!name.is_empty());
+        assert!(!name.ends_with('\n'));
+        meter.untrack_thread(tid);
+        meter.track_thread_auto(tid).unwrap();
+        assert_eq!(meter.thread_names[&tid], name);
+        assert!(meter.track_thread_auto(999_999_999).is_err());
+    }
+}
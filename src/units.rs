@@ -0,0 +1,71 @@
+use Report;
+
+
+/// Unit for `Report::memory_rss_in` and similar methods
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteUnit {
+    /// Bytes
+    Bytes,
+    /// Kibibytes (1024 bytes)
+    KiB,
+    /// Mebibytes (1024 KiB)
+    MiB,
+    /// Gibibytes (1024 MiB)
+    GiB,
+}
+
+impl ByteUnit {
+    /// Returns number of bytes in the unit
+    pub fn bytes(self) -> u64 {
+        match self {
+            ByteUnit::Bytes => 1,
+            ByteUnit::KiB => 1 << 10,
+            ByteUnit::MiB => 1 << 20,
+            ByteUnit::GiB => 1 << 30,
+        }
+    }
+    fn convert(self, bytes: u64) -> f64 {
+        bytes as f64 / self.bytes() as f64
+    }
+}
+
+impl Report {
+    /// Returns `memory_rss` converted to the `unit`
+    pub fn memory_rss_in(&self, unit: ByteUnit) -> f64 {
+        unit.convert(self.memory_rss)
+    }
+    /// Returns `memory_virtual` converted to the `unit`
+    pub fn memory_virtual_in(&self, unit: ByteUnit) -> f64 {
+        unit.convert(self.memory_virtual)
+    }
+    /// Returns `memory_swap` converted to the `unit`
+    pub fn memory_swap_in(&self, unit: ByteUnit) -> f64 {
+        unit.convert(self.memory_swap)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use {Meter, Snapshot};
+    use super::ByteUnit;
+
+    #[test]
+    fn memory_in() {
+        let mut meter = Meter::new(Duration::new(1, 0)).unwrap();
+        meter.snapshots.push_back(Snapshot::new(&HashMap::new()));
+        let mut last = Snapshot::new(&HashMap::new());
+        last.memory_rss = 47185920 + 512;
+        last.memory_virtual = 3 << 30;
+        last.memory_swap = 1536;
+        meter.snapshots.push_back(last);
+        let report = meter.report().unwrap();
+        assert_eq!(report.memory_rss_in(ByteUnit::MiB),
+                   report.memory_rss as f64 / 1048576.0);
+        assert_eq!(report.memory_rss_in(ByteUnit::Bytes), 47186432.);
+        assert_eq!(report.memory_virtual_in(ByteUnit::GiB), 3.);
+        assert_eq!(report.memory_swap_in(ByteUnit::KiB), 1.5);
+    }
+}